@@ -1,9 +1,31 @@
-mod registry;
+mod progress;
+mod server;
 
+use crate::progress::ProgressDisplay;
 use ::actix::prelude::*;
-use legeo::operation::tile_copy;
-use legeo_xyz::grid::Extent;
+use futures::Future;
+use legeo::blank::Blank;
+use legeo::message::{GetInfo, SourceAddr};
+use legeo::operation::{
+    tile_copy_with_progress, tile_zoom_with_progress, CopyOptions, ZoomOptions,
+};
+use legeo::raster::RasterFormat;
+use legeo::registry::Registry;
+use legeo::scheme::{read_tile_list, Scheme};
+use legeo::tileinfo::TileInfo;
+use legeo::transform::{Pipeline, Transform};
+use legeo_xyz::grid::{Extent, Grid};
+use legeo_xyz::tms::TileMatrixSet;
+use log::{error, info};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::net::SocketAddr;
 use std::num::ParseFloatError;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
 /*
@@ -35,39 +57,455 @@ fn parse_extent(numlist: &str) -> Result<Extent, ParseFloatError> {
     })
 }
 
+/// Grid of a built-in tile matrix set or read from a TileMatrixSet JSON file
+fn parse_grid(s: &str) -> Result<Grid, String> {
+    let tms = match TileMatrixSet::builtin(s) {
+        Some(tms) => tms,
+        None => {
+            let json = fs::read_to_string(s).map_err(|e| format!("{}: {}", s, e))?;
+            TileMatrixSet::from_json(&json).map_err(|e| e.to_string())?
+        }
+    };
+    tms.to_grid().map_err(|e| e.to_string())
+}
+
+/// Copy tiles from source to sink, or run one of the subcommands
 #[derive(StructOpt)]
+#[structopt(name = "legeo")]
 struct Cli {
     #[structopt(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
-    /// WGS84 bounding box
+    /// Tile grid [WebMercatorQuad, WorldCRS84Quad, WorldMercatorWGS84Quad or TileMatrixSet JSON file]
+    #[structopt(
+        long,
+        short = "g",
+        default_value = "WebMercatorQuad",
+        parse(try_from_str = "parse_grid")
+    )]
+    grid: Grid,
+    /// WGS84 bounding box [default: source bounds]
+    #[structopt(long, short, parse(try_from_str = "parse_extent"))]
+    bounds: Option<Extent>,
+    /// Bounding box in grid CRS [minx,miny,maxx,maxy]
+    #[structopt(
+        long = "grid-bounds",
+        conflicts_with = "bounds",
+        parse(try_from_str = "parse_extent")
+    )]
+    grid_bounds: Option<Extent>,
+    /// Min zoom (inclusive) [default: source minzoom]
+    #[structopt(long, short = "z")]
+    minzoom: Option<u8>,
+    /// Max zoom (inclusive) [default: source maxzoom]
+    #[structopt(long, short = "Z")]
+    maxzoom: Option<u8>,
+    /// Number of pending I/O operations during copy
+    #[structopt(long, short = "c", default_value = "8")]
+    concurrency: usize,
+    /// Number of source and sink actors, each running in its own thread.
+    /// Single writer sinks, e.g. single file archives, are written by one actor.
+    #[structopt(long, short = "w", default_value = "1")]
+    workers: usize,
+    /// Tile iteration scheme [scanline, pyramid, list]
+    #[structopt(long, short = "s", default_value = "scanline")]
+    scheme: Scheme,
+    /// File with z/x/y tile list for list scheme. Reads from stdin if missing or `-`.
+    #[structopt(long, short = "l", parse(from_os_str))]
+    list: Option<PathBuf>,
+    /// Total number of parts to copy (for processing in parallel)
+    #[structopt(long, default_value = "1")]
+    parts: u32,
+    /// The specific part to copy (0-based)
+    #[structopt(long, default_value = "0")]
+    part: u32,
+    /// Number of retry attempts for failed tiles
+    #[structopt(long, short = "r", default_value = "0")]
+    retry: u32,
+    /// Delay before first retry in ms, doubled for each further attempt
+    #[structopt(long = "retry-delay", default_value = "100")]
+    retry_delay: u64,
+    /// Tile transformation [decompress, gzip, brotli, zstd, png[:colors], jpeg[:quality],
+    /// webp[:quality|lossless]], applied in given order
+    #[structopt(
+        long = "transform",
+        short = "t",
+        raw(number_of_values = "1"),
+        parse(try_from_str = "legeo::transform::builtin")
+    )]
+    transforms: Vec<Arc<dyn Transform>>,
+    /// Write list of failed tiles (z/x/y) to file
+    #[structopt(long = "failed-list", parse(from_os_str))]
+    failed_list: Option<PathBuf>,
+    /// Detect blank tiles [empty vector tiles and transparent[:max_alpha] or uniform[:tolerance] raster tiles]
+    #[structopt(long)]
+    blank: Option<Blank>,
+    /// Do not write blank tiles
+    #[structopt(long = "skip-blank")]
+    skip_blank: bool,
+    /// Write list of blank tiles (z/x/y) to file
+    #[structopt(long = "blank-list", parse(from_os_str))]
+    blank_list: Option<PathBuf>,
+    /// Do not show copy progress
+    #[structopt(long)]
+    withoutprogress: bool,
+    /// Print copy summary as JSON to stdout
+    #[structopt(long)]
+    json: bool,
+    /// source URI
+    srcuri: String,
+    /// sink URI
+    dsturi: String,
+}
+
+#[derive(StructOpt)]
+enum Command {
+    /// Serve tiles over HTTP
+    #[structopt(name = "serve")]
+    Serve(ServeCli),
+    /// Generate missing zoom levels of raster tiles.
+    /// Lower levels are mosaicked from downsampled tiles of the source min zoom,
+    /// higher levels are upscaled from tiles of the source max zoom.
+    #[structopt(name = "zoom")]
+    Zoom(ZoomCli),
+}
+
+fn read_list(path: &Option<PathBuf>) -> legeo::Result<Vec<(u8, u32, u32)>> {
+    match path {
+        Some(path) if path.to_str() != Some("-") => {
+            read_tile_list(BufReader::new(File::open(path)?))
+        }
+        _ => read_tile_list(io::stdin().lock()),
+    }
+}
+
+fn write_tile_list(path: &Path, tiles: &[(u8, u32, u32)]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    for (z, x, y) in tiles {
+        writeln!(file, "{}/{}/{}", z, x, y)?;
+    }
+    file.flush()
+}
+
+/// WGS84 bounds and bounds in grid CRS of the tiles to process.
+/// Falls back to the grid extent, if the source bounds can't be transformed into the grid CRS.
+fn bounds(
+    grid: &Grid,
+    bounds: &Option<Extent>,
+    grid_bounds: &Option<Extent>,
+    info: &TileInfo,
+    defaults: Extent,
+) -> (Extent, Option<Extent>) {
+    if grid_bounds.is_some() {
+        return (defaults, grid_bounds.clone());
+    }
+    if let Some(bounds) = bounds {
+        return (bounds.clone(), None);
+    }
+    let bounds = info.extent().unwrap_or(defaults);
+    if grid.extent_from_wgs84(&bounds).is_none() {
+        info!(
+            "Source bounds can't be transformed into EPSG:{}, using grid extent",
+            grid.srid
+        );
+        return (bounds, Some(grid.extent.clone()));
+    }
+    (bounds, None)
+}
+
+/// Source metadata, empty if not available
+fn source_info(src: &SourceAddr) -> TileInfo {
+    src.get_info
+        .send(GetInfo)
+        .map_err(legeo::Error::from)
+        .and_then(|res| res)
+        .wait()
+        .unwrap_or_else(|e| {
+            error!("Reading source info failed: {}", e);
+            TileInfo::default()
+        })
+}
+
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    legeo_file::file::register(&mut registry);
+    legeo_http::http::register(&mut registry);
+    legeo_mbtiles::mbtiles::register(&mut registry);
+    legeo_null::null::register(&mut registry);
+    legeo_pmtiles::pmtiles::register(&mut registry);
+    registry
+}
+
+#[derive(StructOpt)]
+struct ServeCli {
+    #[structopt(flatten)]
+    verbose: clap_verbosity_flag::Verbosity,
+    /// Address to listen on
+    #[structopt(long, default_value = "127.0.0.1:8080")]
+    bind: SocketAddr,
+    /// Number of source actors, each running in its own thread
+    #[structopt(long, short = "w", default_value = "1")]
+    workers: usize,
+    /// source URI
+    srcuri: String,
+}
+
+// Call example: legeo serve 'mbtiles:///tmp/tiles.mbtiles?mode=ro'
+fn serve(args: ServeCli) {
+    let _ = args.verbose.setup_env_logger("legeo");
+    let code = System::run(move || {
+        let served = registry()
+            .source_pool(&args.srcuri, args.workers)
+            .map_err(|e| e.to_string())
+            .and_then(|src| server::bind(src, &args.bind).map_err(|e| e.to_string()));
+        match served {
+            Ok((addr, server)) => {
+                eprintln!("Serving {} on http://{}/", args.srcuri, addr);
+                actix::spawn(server);
+            }
+            Err(e) => {
+                error!("{}", e);
+                System::current().stop_with_code(1);
+            }
+        }
+    });
+    process::exit(code);
+}
+
+#[derive(StructOpt)]
+struct ZoomCli {
+    #[structopt(flatten)]
+    verbose: clap_verbosity_flag::Verbosity,
+    /// Tile grid [WebMercatorQuad, WorldCRS84Quad, WorldMercatorWGS84Quad or TileMatrixSet JSON file]
     #[structopt(
         long,
-        short,
-        default_value = "-180,-85.0511,180,85.0511",
+        short = "g",
+        default_value = "WebMercatorQuad",
+        parse(try_from_str = "parse_grid")
+    )]
+    grid: Grid,
+    /// WGS84 bounding box [default: source bounds]
+    #[structopt(long, short, parse(try_from_str = "parse_extent"))]
+    bounds: Option<Extent>,
+    /// Bounding box in grid CRS [minx,miny,maxx,maxy]
+    #[structopt(
+        long = "grid-bounds",
+        conflicts_with = "bounds",
         parse(try_from_str = "parse_extent")
     )]
-    bounds: Extent,
-    /// Min zoom (inclusive)
-    #[structopt(long, short = "z", default_value = "0")]
+    grid_bounds: Option<Extent>,
+    /// Min zoom of generated levels (inclusive)
+    #[structopt(long, short = "z")]
     minzoom: u8,
-    /// Max zoom (inclusive)
-    #[structopt(long, short = "Z", default_value = "22")]
+    /// Max zoom of generated levels (inclusive)
+    #[structopt(long, short = "Z")]
     maxzoom: u8,
+    /// Min zoom of source tiles [default: source minzoom]
+    #[structopt(long = "source-minzoom")]
+    source_minzoom: Option<u8>,
+    /// Max zoom of source tiles [default: source maxzoom]
+    #[structopt(long = "source-maxzoom")]
+    source_maxzoom: Option<u8>,
+    /// Output format [png[:colors], jpeg[:quality], webp[:quality|lossless]]
+    /// [default: format of source tiles]
+    #[structopt(long, short = "f")]
+    format: Option<RasterFormat>,
+    /// Write list of failed tiles (z/x/y) to file
+    #[structopt(long = "failed-list", parse(from_os_str))]
+    failed_list: Option<PathBuf>,
+    /// Do not show progress
+    #[structopt(long)]
+    withoutprogress: bool,
+    /// Print summary as JSON to stdout
+    #[structopt(long)]
+    json: bool,
     /// source URI
     srcuri: String,
     /// sink URI
     dsturi: String,
 }
 
-// Call example: legeo 'file:///tmp/legeo?filetype=pbf' 'file:///tmp/legeoout?filetype=pbf'
-fn main() {
-    let args = Cli::from_args();
+// Call example: legeo zoom -z 15 -Z 16 'mbtiles:///tmp/z14.mbtiles' 'file:///tmp/z16'
+fn zoom(args: ZoomCli) {
     let _ = args.verbose.setup_env_logger("legeo");
-    System::run(|| {
-        let src = registry::TileInput::from_uri(args.srcuri);
-        let dst = registry::TileOutput::from_uri(args.dsturi);
-        tile_copy(src, dst, args.bounds, args.minzoom, args.maxzoom);
+    let code = System::run(move || {
+        let registry = registry();
+        let endpoints = registry
+            .source_pool(&args.srcuri, 1)
+            .and_then(|src| registry.sink_pool(&args.dsturi, 1).map(|dst| (src, dst)));
+        match endpoints {
+            Ok((src, dst)) => {
+                let info = source_info(&src);
+                let defaults = ZoomOptions::default();
+                let (bounds, grid_bounds) = bounds(
+                    &args.grid,
+                    &args.bounds,
+                    &args.grid_bounds,
+                    &info,
+                    defaults.bounds,
+                );
+                let options = ZoomOptions {
+                    grid: args.grid.clone(),
+                    bounds,
+                    grid_bounds,
+                    minzoom: args.minzoom,
+                    maxzoom: args.maxzoom,
+                    source_minzoom: args
+                        .source_minzoom
+                        .or(info.minzoom)
+                        .unwrap_or(defaults.source_minzoom),
+                    source_maxzoom: args
+                        .source_maxzoom
+                        .or(info.maxzoom)
+                        .unwrap_or(defaults.source_maxzoom),
+                    format: args.format,
+                };
+                let show_progress = !args.withoutprogress;
+                let mut display = ProgressDisplay::new();
+                let res = tile_zoom_with_progress(src, dst, &options, |stats| {
+                    if show_progress {
+                        display.update(stats);
+                    }
+                });
+                display.finish();
+                let stats = match res {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        error!("{}", e);
+                        System::current().stop_with_code(1);
+                        return;
+                    }
+                };
+                if let Some(path) = &args.failed_list {
+                    if let Err(e) = write_tile_list(path, &stats.failed_tiles) {
+                        error!("Writing {:?} failed: {}", path, e);
+                    }
+                }
+                if args.json {
+                    println!("{}", progress::summary_json(&stats));
+                } else {
+                    eprintln!("{}", progress::summary(&stats));
+                }
+                System::current().stop();
+            }
+            Err(e) => {
+                error!("{}", e);
+                System::current().stop_with_code(1);
+            }
+        }
+    });
+    process::exit(code);
+}
+
+fn main() {
+    // Copy arguments are parsed only without subcommand
+    let matches = Command::augment_clap(Cli::clap())
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .get_matches();
+    match Command::from_subcommand(matches.subcommand()) {
+        Some(Command::Serve(args)) => serve(args),
+        Some(Command::Zoom(args)) => zoom(args),
+        None => copy(Cli::from_clap(&matches)),
+    }
+}
 
-        System::current().stop();
+// Call example: legeo 'file:///tmp/legeo?filetype=pbf' 'file:///tmp/legeoout?filetype=pbf'
+fn copy(args: Cli) {
+    let _ = args.verbose.setup_env_logger("legeo");
+    let scheme = match (&args.scheme, &args.list) {
+        (Scheme::List(_), list) | (_, list @ Some(_)) => match read_list(list) {
+            Ok(tiles) => Scheme::List(tiles),
+            Err(e) => {
+                error!("Reading tile list failed: {}", e);
+                process::exit(1);
+            }
+        },
+        (scheme, None) => scheme.clone(),
+    };
+    if args.part >= args.parts {
+        error!("--part must be less than --parts");
+        process::exit(1);
+    }
+    if args.blank.is_none() && (args.skip_blank || args.blank_list.is_some()) {
+        error!("--skip-blank and --blank-list require --blank");
+        process::exit(1);
+    }
+    let code = System::run(move || {
+        let registry = registry();
+        let endpoints = registry
+            .source_pool(&args.srcuri, args.workers)
+            .and_then(|src| {
+                registry
+                    .sink_pool(&args.dsturi, args.workers)
+                    .map(|dst| (src, dst))
+            });
+        match endpoints {
+            Ok((src, dst)) => {
+                let info = source_info(&src);
+                let defaults = CopyOptions::default();
+                let (bounds, grid_bounds) = bounds(
+                    &args.grid,
+                    &args.bounds,
+                    &args.grid_bounds,
+                    &info,
+                    defaults.bounds,
+                );
+                let options = CopyOptions {
+                    grid: args.grid.clone(),
+                    bounds,
+                    grid_bounds,
+                    minzoom: args.minzoom.or(info.minzoom).unwrap_or(defaults.minzoom),
+                    maxzoom: args.maxzoom.or(info.maxzoom).unwrap_or(defaults.maxzoom),
+                    concurrency: args.concurrency,
+                    retry: args.retry,
+                    retry_delay: Duration::from_millis(args.retry_delay),
+                    parts: args.parts,
+                    part: args.part,
+                    scheme,
+                    transforms: Pipeline::from(args.transforms.clone()),
+                    blank: args.blank,
+                    skip_blank: args.skip_blank,
+                    blank_list: args.blank_list.is_some(),
+                };
+                let show_progress = !args.withoutprogress;
+                let mut display = ProgressDisplay::new();
+                let res = tile_copy_with_progress(src, dst, &options, |stats| {
+                    if show_progress {
+                        display.update(stats);
+                    }
+                });
+                display.finish();
+                let stats = match res {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        error!("{}", e);
+                        System::current().stop_with_code(1);
+                        return;
+                    }
+                };
+                let lists = [
+                    (&args.failed_list, &stats.failed_tiles),
+                    (&args.blank_list, &stats.blank_tiles),
+                ];
+                for (path, tiles) in &lists {
+                    if let Some(path) = path {
+                        if let Err(e) = write_tile_list(path, tiles) {
+                            error!("Writing {:?} failed: {}", path, e);
+                        }
+                    }
+                }
+                if args.json {
+                    println!("{}", progress::summary_json(&stats));
+                } else {
+                    eprintln!("{}", progress::summary(&stats));
+                }
+                System::current().stop();
+            }
+            Err(e) => {
+                error!("{}", e);
+                System::current().stop_with_code(1);
+            }
+        }
     });
+    process::exit(code);
 }
@@ -0,0 +1,126 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Copy progress display and summary
+
+use legeo::operation::CopyStats;
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Minimal time between two progress line updates
+const UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Progress line on stderr
+pub struct ProgressDisplay {
+    last_update: Option<Instant>,
+}
+
+impl ProgressDisplay {
+    pub fn new() -> ProgressDisplay {
+        ProgressDisplay { last_update: None }
+    }
+
+    pub fn update(&mut self, stats: &CopyStats) {
+        let done = stats.processed() >= stats.total;
+        if let Some(last_update) = self.last_update {
+            if !done && last_update.elapsed() < UPDATE_INTERVAL {
+                return;
+            }
+        }
+        self.last_update = Some(Instant::now());
+        let percent = if stats.total > 0 {
+            stats.processed() as f64 * 100.0 / stats.total as f64
+        } else {
+            100.0
+        };
+        let eta = stats
+            .eta()
+            .map_or("-".to_string(), |eta| format_duration(&eta));
+        let mut stderr = io::stderr();
+        let _ = write!(
+            stderr,
+            "\r{:5.1}% {}/{} tiles, {} copied, {} skipped, {} failed, {}, {:.0} tiles/s, ETA {}  ",
+            percent,
+            stats.processed(),
+            stats.total,
+            stats.copied,
+            stats.skipped,
+            stats.failed,
+            format_bytes(stats.bytes),
+            stats.tiles_per_sec(),
+            eta
+        );
+        let _ = stderr.flush();
+    }
+
+    pub fn finish(&self) {
+        if self.last_update.is_some() {
+            eprintln!();
+        }
+    }
+}
+
+/// Human readable copy summary
+pub fn summary(stats: &CopyStats) -> String {
+    let blank = if stats.blank > 0 {
+        format!(", {} blank", stats.blank)
+    } else {
+        String::new()
+    };
+    format!(
+        "{} tiles copied, {} skipped, {} failed{} ({}) in {} ({:.0} tiles/s)",
+        stats.copied,
+        stats.skipped,
+        stats.failed,
+        blank,
+        format_bytes(stats.bytes),
+        format_duration(&stats.elapsed),
+        stats.tiles_per_sec()
+    )
+}
+
+/// Copy summary as JSON object
+pub fn summary_json(stats: &CopyStats) -> String {
+    json!({
+        "total": stats.total,
+        "copied": stats.copied,
+        "skipped": stats.skipped,
+        "failed": stats.failed,
+        "blank": stats.blank,
+        "bytes": stats.bytes,
+        "elapsed": stats.elapsed.as_secs_f64(),
+        "tiles_per_sec": stats.tiles_per_sec(),
+    })
+    .to_string()
+}
+
+fn format_duration(duration: &Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(format_bytes(1000), "1000 B");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GB");
+    assert_eq!(format_duration(&Duration::from_secs(3723)), "1:02:03");
+}
@@ -0,0 +1,304 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! HTTP tile server
+
+use futures::future;
+use futures::Future;
+use hyper::header::{self, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use legeo::message::{GetInfo, GetTile, SourceAddr};
+use legeo::tileinfo::TileInfo;
+use legeo::Error;
+use legeo_xyz::tile::TileCoord;
+use log::{error, info};
+use std::net::SocketAddr;
+
+//  Inspired by https://github.com/mojodna/tessera
+//
+// * `/{z}/{x}/{y}.{format}`: Tiles
+// * `/index.json`: TileJSON
+
+type ResponseFuture = Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send>;
+
+/// Bind HTTP server serving tiles of `src`.
+/// The returned future has to be spawned within a running actix system.
+pub fn bind(
+    src: SourceAddr,
+    addr: &SocketAddr,
+) -> hyper::Result<(SocketAddr, impl Future<Item = (), Error = ()>)> {
+    let server = Server::try_bind(addr)?;
+    let new_service = move || {
+        let src = src.clone();
+        service_fn(move |req| handle(&src, req))
+    };
+    let server = server.serve(new_service);
+    let local_addr = server.local_addr();
+    Ok((
+        local_addr,
+        server.map_err(|e| error!("Server error: {}", e)),
+    ))
+}
+
+fn handle(src: &SourceAddr, req: Request<Body>) -> ResponseFuture {
+    info!("{} {}", req.method(), req.uri());
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Box::new(future::ok(status(StatusCode::METHOD_NOT_ALLOWED)));
+    }
+    let path = req.uri().path();
+    if path == "/index.json" {
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .unwrap_or("localhost")
+            .to_string();
+        return Box::new(tilejson_response(src, host));
+    }
+    match parse_tile_path(path) {
+        Some((z, x, y)) => {
+            let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+            Box::new(tile_response(src, z, x, y, if_none_match))
+        }
+        None => Box::new(future::ok(status(StatusCode::NOT_FOUND))),
+    }
+}
+
+/// Parse tile path `/{z}/{x}/{y}.{ext}`
+fn parse_tile_path(path: &str) -> Option<(u8, u32, u32)> {
+    let mut parts = path.trim_start_matches('/').split('/');
+    let z: u8 = parts.next()?.parse().ok()?;
+    let x: u32 = parts.next()?.parse().ok()?;
+    let y: u32 = parts.next()?.split('.').next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    TileCoord::new(z, x, y).map(|tile| (tile.z, tile.x, tile.y))
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+fn error_response(e: Error) -> Response<Body> {
+    error!("{}", e);
+    status(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Tile response. Missing tiles return 404, empty tiles 204.
+fn tile_response(
+    src: &SourceAddr,
+    z: u8,
+    x: u32,
+    y: u32,
+    if_none_match: Option<HeaderValue>,
+) -> impl Future<Item = Response<Body>, Error = hyper::Error> {
+    src.get_tile
+        .send(GetTile { z, x, y })
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .then(move |res| {
+            let response = match res {
+                Ok(Some(tile)) => {
+                    let etag = tile.headers().into_iter().find(|(name, _)| *name == "ETag");
+                    let not_modified = match (&etag, &if_none_match) {
+                        (Some((_, etag)), Some(if_none_match)) => {
+                            if_none_match.to_str().ok() == Some(etag.as_str())
+                        }
+                        _ => false,
+                    };
+                    let mut response = if not_modified {
+                        status(StatusCode::NOT_MODIFIED)
+                    } else if tile.data.is_empty() {
+                        status(StatusCode::NO_CONTENT)
+                    } else {
+                        Response::new(Body::from(tile.data.clone()))
+                    };
+                    let headers = response.headers_mut();
+                    for (name, value) in tile.headers() {
+                        if let Ok(value) = HeaderValue::from_str(&value) {
+                            headers.insert(name, value);
+                        }
+                    }
+                    headers.insert(
+                        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                        HeaderValue::from_static("*"),
+                    );
+                    response
+                }
+                Ok(None) => status(StatusCode::NOT_FOUND),
+                Err(e) => error_response(e),
+            };
+            Ok(response)
+        })
+}
+
+/// TileJSON response with tile URL template for `host`
+fn tilejson_response(
+    src: &SourceAddr,
+    host: String,
+) -> impl Future<Item = Response<Body>, Error = hyper::Error> {
+    src.get_info
+        .send(GetInfo)
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .then(move |res| {
+            let response = match res {
+                Ok(info) => {
+                    let mut response = Response::new(Body::from(tilejson(&info, &host)));
+                    let headers = response.headers_mut();
+                    headers.insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                    headers.insert(
+                        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                        HeaderValue::from_static("*"),
+                    );
+                    response
+                }
+                Err(e) => error_response(e),
+            };
+            Ok(response)
+        })
+}
+
+fn tilejson(info: &TileInfo, host: &str) -> String {
+    let ext = info.format.as_ref().map_or("png", |format| format.as_str());
+    let mut json: serde_json::Value = serde_json::from_str(&info.to_tilejson()).unwrap_or_default();
+    json["tiles"] = serde_json::json!([format!("http://{}/{{z}}/{{x}}/{{y}}.{}", host, ext)]);
+    json.to_string()
+}
+
+#[cfg(test)]
+fn http_get(addr: &SocketAddr, path: &str, headers: &str) -> (u16, String, Vec<u8>) {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n{}\r\n",
+        path, addr, headers
+    )
+    .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let head = String::from_utf8_lossy(&response[..split]).to_string();
+    let status = head[9..12].parse().unwrap();
+    (status, head, response[split + 4..].to_vec())
+}
+
+#[test]
+fn test_parse_path() {
+    assert_eq!(parse_tile_path("/3/7/6.pbf"), Some((3, 7, 6)));
+    assert_eq!(parse_tile_path("/3/7/6"), Some((3, 7, 6)));
+    assert_eq!(parse_tile_path("/3/8/6.png"), None);
+    assert_eq!(parse_tile_path("/3/7/6/1.png"), None);
+    assert_eq!(parse_tile_path("/favicon.ico"), None);
+}
+
+/// Serve `uri` and run `requests` of `(path, headers)`
+#[cfg(test)]
+fn serve_requests(uri: &str, requests: Vec<(String, String)>) -> Vec<(u16, String, Vec<u8>)> {
+    use actix::prelude::*;
+    use std::sync::{Arc, Mutex};
+
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let thread_responses = responses.clone();
+    let uri = uri.to_string();
+    System::run(move || {
+        let src = crate::registry().source(&uri).unwrap();
+        let (addr, server) = bind(src, &"127.0.0.1:0".parse().unwrap()).unwrap();
+        actix::spawn(server);
+        let sys = System::current();
+        std::thread::spawn(move || {
+            for (path, headers) in requests {
+                let response = http_get(&addr, &path, &headers);
+                thread_responses.lock().unwrap().push(response);
+            }
+            sys.stop();
+        });
+    });
+    let responses = responses.lock().unwrap().clone();
+    responses
+}
+
+#[test]
+fn test_serve_mbtiles() {
+    use legeo::tileconnector::Tileconnector;
+    use legeo::tilesink::Tilesink;
+    use legeo_mbtiles::mbtiles::Mbtiles;
+
+    let _ = std::fs::remove_file("/tmp/legeo_serve.mbtiles");
+    let sink = Mbtiles::load("mbtiles:///tmp/legeo_serve.mbtiles").unwrap();
+    sink.put_tile(1, 0, 1, b"\x89PNG\r\n\x1a\n".to_vec())
+        .unwrap();
+    sink.put_tile(1, 1, 1, Vec::new()).unwrap();
+    sink.put_info(&TileInfo {
+        format: Some("png".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    drop(sink);
+
+    let uri = "mbtiles:///tmp/legeo_serve.mbtiles?mode=ro";
+    let get = |path: &str| (path.to_string(), String::new());
+    let responses = serve_requests(
+        uri,
+        vec![
+            get("/1/0/1.png"),
+            get("/1/1/1.png"),
+            get("/1/0/0.png"),
+            get("/1/2/0.png"),
+            get("/index.json"),
+        ],
+    );
+    let (status, head, body) = &responses[0];
+    assert_eq!(*status, 200);
+    assert!(head.contains("content-type: image/png"));
+    assert_eq!(body, b"\x89PNG\r\n\x1a\n");
+    assert_eq!(responses[1].0, 204);
+    assert_eq!(responses[2].0, 404);
+    assert_eq!(responses[3].0, 404);
+    let (status, _, body) = &responses[4];
+    assert_eq!(*status, 200);
+    let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+    assert!(json["tiles"][0]
+        .as_str()
+        .unwrap()
+        .ends_with("/{z}/{x}/{y}.png"));
+
+    let etag = head
+        .lines()
+        .find(|line| line.starts_with("etag: "))
+        .unwrap()
+        .trim_start_matches("etag: ");
+    let responses = serve_requests(
+        uri,
+        vec![(
+            "/1/0/1.png".to_string(),
+            format!("If-None-Match: {}\r\n", etag),
+        )],
+    );
+    assert_eq!(responses[0].0, 304);
+}
+
+#[test]
+fn test_serve_file() {
+    std::fs::create_dir_all("/tmp/legeo_serve/0/0").unwrap();
+    std::fs::write("/tmp/legeo_serve/0/0/0.pbf", [0x1F, 0x8B, 0x08, 0x00]).unwrap();
+    let responses = serve_requests(
+        "file:///tmp/legeo_serve?filetype=pbf",
+        vec![("/0/0/0.pbf".to_string(), String::new())],
+    );
+    let (status, head, _) = &responses[0];
+    assert_eq!(*status, 200);
+    assert!(head.contains("content-type: application/x-protobuf"));
+    assert!(head.contains("content-encoding: gzip"));
+    assert!(head.contains("last-modified: "));
+}
@@ -6,22 +6,32 @@
 //! Reads/writes tiles from/to the filesystem.
 
 use ::actix::prelude::*;
-use legeo::message::{GetTile, GetTileResult, PutTile, PutTileResult};
+use legeo::message::{
+    GetInfo, GetInfoResult, GetTile, GetTileResult, PutInfo, PutInfoResult, PutTile, PutTileResult,
+    StopWriting, StopWritingResult,
+};
+use legeo::registry::Registry;
+use legeo::tile::{stat_etag, Tile};
 use legeo::tileconnector::Tileconnector;
+use legeo::tileinfo::TileInfo;
 use legeo::tilesink::Tilesink;
 use legeo::tilesource::Tilesource;
-use log::debug;
-use std::borrow::Cow;
+use legeo::tiletype::{self, TileFormat};
+use legeo::Result;
+use log::{debug, warn};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::Read;
-use std::io::Write;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
-use url::{self, Url};
+use url::Url;
 
 pub struct FileBackend {
     basepath: String,
-    filetype: String,
+    /// File extension. Taken from the tileset format or detected from tile data if `None`.
+    filetype: Option<String>,
+    /// File extension of the tileset format written with `put_info`
+    format_ext: RefCell<Option<&'static str>>,
     safe: bool,
 }
 
@@ -47,43 +57,86 @@ impl FileBackend {
 
 impl Tileconnector for FileBackend {
     /// Create FileBackend with base path and format information from `uri`
-    fn load(uri: &str) -> Result<Self, url::ParseError> {
+    fn load(uri: &str) -> Result<Self> {
         let uri = Url::parse(uri)?;
         let basepath = uri.path().to_string();
         let params: HashMap<_, _> = uri.query_pairs().collect();
-        let filetype = params
-            .get("filetype")
-            .unwrap_or(&Cow::from("png"))
-            .to_string();
-        let safe = params.get("safe").map_or(false, |v| v == "true");
+        let filetype = params.get("filetype").map(|t| t.to_string());
+        let safe = params.get("safe").is_some_and(|v| v == "true");
         Ok(FileBackend {
             basepath,
             filetype,
+            format_ext: RefCell::new(None),
             safe,
         })
     }
 }
 
 impl Tilesource for FileBackend {
-    fn get_tile(&self, z: u8, x: u32, y: u32) -> std::io::Result<Vec<u8>> {
-        let path = self.get_path(z, x, y, &self.filetype);
-        debug!("GetTile {:?}", path);
-        let mut file = File::open(path)?;
-        let mut content = Vec::new();
-        file.read_to_end(&mut content)?;
-        Ok(content)
+    /// Tile with file modification time and ETag from file size and mtime
+    fn get_tile(&self, z: u8, x: u32, y: u32) -> Result<Option<Tile>> {
+        let extensions = match &self.filetype {
+            Some(filetype) => vec![filetype.as_str()],
+            None => TileFormat::ALL
+                .iter()
+                .chain(&[TileFormat::Unknown])
+                .map(|format| format.extension())
+                .collect(),
+        };
+        for ext in extensions {
+            let path = self.get_path(z, x, y, ext);
+            debug!("GetTile {:?}", path);
+            let mut file = match File::open(path) {
+                Ok(file) => file,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)?;
+            let mut tile = Tile::new(content);
+            if let Ok(mtime) = file.metadata().and_then(|meta| meta.modified()) {
+                let size = tile.data.len() as u64;
+                tile = tile.with_mtime(mtime).with_etag(stat_etag(size, mtime));
+            }
+            return Ok(Some(tile));
+        }
+        Ok(None)
     }
 }
 
 impl Tilesink for FileBackend {
-    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> std::io::Result<()> {
-        let path = self.get_path(z, x, y, &self.filetype);
+    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<()> {
+        let format_ext = *self.format_ext.borrow();
+        let ext = match (&self.filetype, format_ext) {
+            (Some(filetype), _) => filetype.as_str(),
+            (None, Some(ext)) => ext,
+            (None, None) => tiletype::detect(&data).format.extension(),
+        };
+        let path = self.get_path(z, x, y, ext);
         debug!("PutTile {:?}", path);
         fs::create_dir_all(path.parent().unwrap())?;
         let mut f = File::create(path)?;
         f.write_all(&data)?;
         Ok(())
     }
+
+    /// Use extension of tileset format for written tiles
+    fn put_info(&self, info: &TileInfo) -> Result<()> {
+        let format = match info.format.as_ref().map(|name| TileFormat::from_name(name)) {
+            Some(format) if format != TileFormat::Unknown => format,
+            _ => return Ok(()),
+        };
+        match &self.filetype {
+            Some(filetype) if filetype != format.extension() => warn!(
+                "Writing {} tiles with file extension `{}`",
+                format.name(),
+                filetype
+            ),
+            _ => {}
+        }
+        *self.format_ext.borrow_mut() = Some(format.extension());
+        Ok(())
+    }
 }
 
 impl Actor for FileBackend {
@@ -98,6 +151,14 @@ impl Handler<GetTile> for FileBackend {
     }
 }
 
+impl Handler<GetInfo> for FileBackend {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        self.get_info()
+    }
+}
+
 impl Handler<PutTile> for FileBackend {
     type Result = PutTileResult;
 
@@ -106,6 +167,28 @@ impl Handler<PutTile> for FileBackend {
     }
 }
 
+impl Handler<PutInfo> for FileBackend {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        self.put_info(&msg.info)
+    }
+}
+
+impl Handler<StopWriting> for FileBackend {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        self.stop_writing()
+    }
+}
+
+/// Register `file://` source and sink
+pub fn register(registry: &mut Registry) {
+    registry.register_source::<FileBackend>("file");
+    registry.register_sink::<FileBackend>("file");
+}
+
 #[test]
 fn test_tile() {
     let backend = FileBackend::load("file:///tmp/legeo?filetype=txt").unwrap();
@@ -113,11 +196,17 @@ fn test_tile() {
     let _ = backend.put_tile(3, 7, 7, tile_data.to_vec());
     let mut file = File::open("/tmp/legeo/3/7/7.txt").unwrap();
     let mut content = [0; 5];
-    file.read(&mut content).unwrap();
+    file.read_exact(&mut content).unwrap();
     assert_eq!(&content, tile_data);
 
-    let tile = backend.get_tile(3, 7, 7).unwrap();
-    assert_eq!(&tile, tile_data);
+    let tile = backend.get_tile(3, 7, 7).unwrap().unwrap();
+    assert_eq!(&tile.data, tile_data);
+    let mtime = fs::metadata("/tmp/legeo/3/7/7.txt")
+        .unwrap()
+        .modified()
+        .unwrap();
+    assert_eq!(tile.mtime, Some(mtime));
+    assert_eq!(tile.etag, Some(stat_etag(5, mtime)));
 }
 
 #[test]
@@ -127,9 +216,45 @@ fn test_tile_safe() {
     let _ = backend.put_tile(3, 7, 7, tile_data.to_vec());
     let mut file = File::open("/tmp/legeo/3/000/007/000/007.txt").unwrap();
     let mut content = [0; 5];
-    file.read(&mut content).unwrap();
+    file.read_exact(&mut content).unwrap();
     assert_eq!(&content, tile_data);
 
-    let tile = backend.get_tile(3, 7, 7).unwrap();
-    assert_eq!(&tile, tile_data);
+    let tile = backend.get_tile(3, 7, 7).unwrap().unwrap();
+    assert_eq!(&tile.data, tile_data);
+}
+
+#[test]
+fn test_missing_tile() {
+    let backend = FileBackend::load("file:///tmp/legeo?filetype=txt").unwrap();
+    let tile = backend.get_tile(3, 7, 8).unwrap();
+    assert_eq!(tile, None);
+}
+
+#[test]
+fn test_detected_filetype() {
+    let backend = FileBackend::load("file:///tmp/legeo_detect").unwrap();
+    let png = b"\x89PNG\r\n\x1a\n".to_vec();
+    backend.put_tile(1, 0, 1, png.clone()).unwrap();
+    assert!(Path::new("/tmp/legeo_detect/1/0/1.png").exists());
+    let tile = backend.get_tile(1, 0, 1).unwrap().unwrap();
+    assert_eq!(tile.data, png);
+    assert_eq!(tile.content_type, "image/png");
+
+    let mvt = b"\x1a\x04\x0a\x02id".to_vec();
+    backend.put_tile(1, 1, 1, mvt.clone()).unwrap();
+    assert!(Path::new("/tmp/legeo_detect/1/1/1.pbf").exists());
+    assert_eq!(backend.get_tile(1, 1, 1).unwrap().unwrap().data, mvt);
+}
+
+#[test]
+fn test_info_filetype() {
+    let backend = FileBackend::load("file:///tmp/legeo_info").unwrap();
+    backend
+        .put_info(&TileInfo {
+            format: Some("webp".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    backend.put_tile(1, 0, 0, Vec::new()).unwrap();
+    assert!(Path::new("/tmp/legeo_info/1/0/0.webp").exists());
 }
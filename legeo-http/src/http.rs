@@ -0,0 +1,323 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Reads tiles from HTTP servers with XYZ URL templates.
+
+use ::actix::prelude::*;
+use legeo::message::{GetInfo, GetInfoResult, GetTile, GetTileResult};
+use legeo::registry::Registry;
+use legeo::tile::Tile;
+use legeo::tileconnector::Tileconnector;
+use legeo::tilesource::Tilesource;
+use legeo::{Error, Result};
+use legeo_xyz::tile::TileCoord;
+use log::debug;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::form_urlencoded;
+
+//  Similar to https://github.com/mojodna/tilelive-http
+//
+// URI: `http://{s}.tile.example.com/{z}/{x}/{y}.png#timeout=5000&rate=10`
+//
+// Placeholders:
+// * `{z}`, `{x}`, `{y}`: XYZ tile coordinates
+// * `{-y}`: TMS row
+// * `{s}`: Subdomain, selected from `subdomains` by tile position
+//
+// Options in the URI fragment, which is not sent to the server:
+// * `header=Name:Value`: Request header (repeatable)
+// * `timeout`: Request timeout in ms (default: 30000)
+// * `rate`: Max requests per second of all sources with the same URI
+// * `subdomains`: Comma separated list (default: a,b,c)
+
+pub struct HttpSource {
+    template: String,
+    subdomains: Vec<String>,
+    headers: Vec<(String, String)>,
+    agent: ureq::Agent,
+    /// Min time between two requests
+    interval: Option<Duration>,
+    /// Time of the last request, shared by all sources with the same URI
+    last_request: RateLimiter,
+}
+
+type RateLimiter = Arc<Mutex<Option<Instant>>>;
+
+/// Rate limiter of a URI, shared between the workers of a pool
+fn rate_limiter(uri: &str) -> RateLimiter {
+    static LIMITERS: OnceLock<Mutex<HashMap<String, RateLimiter>>> = OnceLock::new();
+    let mut limiters = LIMITERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    limiters.entry(uri.to_string()).or_default().clone()
+}
+
+impl HttpSource {
+    /// URL of tile
+    fn tile_url(&self, tile: TileCoord) -> String {
+        let TileCoord { z, x, y } = tile;
+        let mut url = self
+            .template
+            .replace("{z}", &z.to_string())
+            .replace("{x}", &x.to_string())
+            .replace("{y}", &y.to_string())
+            .replace("{-y}", &tile.tms_y().to_string());
+        if !self.subdomains.is_empty() {
+            let s = &self.subdomains[(x as usize + y as usize) % self.subdomains.len()];
+            url = url.replace("{s}", s);
+        }
+        url
+    }
+
+    /// Wait until the request rate allows the next request
+    fn throttle(&self) {
+        if let Some(interval) = self.interval {
+            let now = Instant::now();
+            // Reserve the next free slot, so concurrent workers don't wait for the same one
+            let slot = {
+                let mut last = self.last_request.lock().unwrap_or_else(|e| e.into_inner());
+                let slot = last.map_or(now, |last| (last + interval).max(now));
+                *last = Some(slot);
+                slot
+            };
+            if slot > now {
+                thread::sleep(slot - now);
+            }
+        }
+    }
+}
+
+fn invalid_param(name: &str, value: &str) -> Error {
+    Error::UnsupportedParameter {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
+
+impl Tileconnector for HttpSource {
+    /// Create HttpSource from URL template with options in the fragment
+    fn load(uri: &str) -> Result<Self> {
+        let (template, fragment) = match uri.find('#') {
+            Some(pos) => (&uri[..pos], &uri[pos + 1..]),
+            None => (uri, ""),
+        };
+        // Validate URL with placeholders replaced
+        url::Url::parse(&template.replace(['{', '}'], ""))?;
+        let mut subdomains = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut headers = Vec::new();
+        let mut timeout = Duration::from_secs(30);
+        let mut interval = None;
+        for (name, value) in form_urlencoded::parse(fragment.as_bytes()) {
+            match name.as_ref() {
+                "header" => {
+                    let mut parts = value.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(value)) => {
+                            headers.push((name.trim().to_string(), value.trim().to_string()))
+                        }
+                        _ => return Err(invalid_param(&name, &value)),
+                    }
+                }
+                "timeout" => {
+                    let ms = value.parse().map_err(|_| invalid_param(&name, &value))?;
+                    timeout = Duration::from_millis(ms);
+                }
+                "rate" => {
+                    let rate: f64 = value.parse().map_err(|_| invalid_param(&name, &value))?;
+                    if rate <= 0.0 {
+                        return Err(invalid_param(&name, &value));
+                    }
+                    interval = Some(Duration::from_secs_f64(1.0 / rate));
+                }
+                "subdomains" => {
+                    subdomains = value.split(',').map(|s| s.to_string()).collect();
+                }
+                _ => return Err(invalid_param(&name, &value)),
+            }
+        }
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        Ok(HttpSource {
+            template: template.to_string(),
+            subdomains,
+            headers,
+            agent,
+            interval,
+            last_request: rate_limiter(uri),
+        })
+    }
+}
+
+impl Tilesource for HttpSource {
+    /// Responses with status 404 or 204 are missing tiles
+    fn get_tile(&self, z: u8, x: u32, y: u32) -> Result<Option<Tile>> {
+        let url = match TileCoord::new(z, x, y) {
+            Some(tile) => self.tile_url(tile),
+            None => return Ok(None),
+        };
+        debug!("GET {}", url);
+        self.throttle();
+        let mut request = self.agent.get(&url);
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(Error::backend(e)),
+        };
+        if response.status() == 204 {
+            return Ok(None);
+        }
+        let etag = response
+            .header("ETag")
+            .map(|etag| etag.trim_start_matches("W/").trim_matches('"').to_string());
+        let mtime = response
+            .header("Last-Modified")
+            .and_then(|date| httpdate::parse_http_date(date).ok());
+        let mut data = Vec::new();
+        response.into_reader().read_to_end(&mut data)?;
+        let mut tile = Tile::new(data);
+        tile.etag = etag;
+        tile.mtime = mtime;
+        Ok(Some(tile))
+    }
+}
+
+impl Actor for HttpSource {
+    type Context = Context<Self>;
+}
+
+impl Handler<GetTile> for HttpSource {
+    type Result = GetTileResult;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        self.get_tile(msg.z, msg.x, msg.y)
+    }
+}
+
+impl Handler<GetInfo> for HttpSource {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        self.get_info()
+    }
+}
+
+/// Register `http://` and `https://` sources
+pub fn register(registry: &mut Registry) {
+    registry.register_source::<HttpSource>("http");
+    registry.register_source::<HttpSource>("https");
+}
+
+/// Local test server responding to `/{z}/{x}/{y}.png` requests.
+/// Sends back the received `X-Token` header in the tile data.
+#[cfg(test)]
+fn test_server() -> std::net::SocketAddr {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut token = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if line.to_lowercase().starts_with("x-token:") {
+                    token = line[8..].trim().to_string();
+                }
+            }
+            let path = request.split(' ').nth(1).unwrap_or("");
+            let response = match path {
+                "/0/0/0.png" => format!(
+                    "HTTP/1.1 200 OK\r\nETag: \"abc\"\r\nLast-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\nContent-Length: {}\r\n\r\ntile{}",
+                    4 + token.len(),
+                    token
+                ),
+                "/1/1/1.png" => "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+                "/2/0/0.png" => "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n".to_string(),
+                "/3/0/0.png" => {
+                    thread::sleep(Duration::from_millis(500));
+                    "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_string()
+                }
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    addr
+}
+
+#[test]
+fn test_tile_url() {
+    let source = HttpSource::load("https://{s}.tile.example.com/{z}/{x}/{-y}.png?key={y}").unwrap();
+    assert_eq!(
+        source.tile_url(TileCoord { z: 3, x: 1, y: 2 }),
+        "https://a.tile.example.com/3/1/5.png?key=2"
+    );
+    assert_eq!(
+        source.tile_url(TileCoord { z: 3, x: 2, y: 2 }),
+        "https://b.tile.example.com/3/2/5.png?key=2"
+    );
+    let source = HttpSource::load("http://{s}.example.com/{z}/{x}/{y}#subdomains=t1,t2").unwrap();
+    assert_eq!(
+        source.tile_url(TileCoord { z: 1, x: 0, y: 1 }),
+        "http://t2.example.com/1/0/1"
+    );
+
+    match HttpSource::load("http://example.com/{z}/{x}/{y}#rate=fast") {
+        Err(Error::UnsupportedParameter { name, .. }) => assert_eq!(name, "rate"),
+        _ => panic!("UnsupportedParameter expected"),
+    }
+}
+
+#[test]
+fn test_get_tile() {
+    let addr = test_server();
+    let template = format!("http://{}/{{z}}/{{x}}/{{y}}.png", addr);
+    let source =
+        HttpSource::load(&format!("{}#header=X-Token:secret&timeout=200", template)).unwrap();
+    let tile = source.get_tile(0, 0, 0).unwrap().unwrap();
+    assert_eq!(tile.data, b"tilesecret");
+    assert_eq!(tile.etag, Some("abc".to_string()));
+    assert!(tile.mtime.is_some());
+    assert_eq!(source.get_tile(1, 1, 1).unwrap(), None);
+    assert_eq!(source.get_tile(1, 0, 0).unwrap(), None);
+    assert!(source.get_tile(2, 0, 0).is_err());
+    // Timeout
+    assert!(source.get_tile(3, 0, 0).is_err());
+
+    let source = HttpSource::load(&format!("{}#rate=20", template)).unwrap();
+    let start = Instant::now();
+    for _ in 0..3 {
+        source.get_tile(0, 0, 0).unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // Rate limit is shared by the workers of a pool
+    let uri = format!("{}#rate=20&timeout=1000", template);
+    let sources = [
+        HttpSource::load(&uri).unwrap(),
+        HttpSource::load(&uri).unwrap(),
+    ];
+    let start = Instant::now();
+    for source in sources.iter().cycle().take(3) {
+        source.get_tile(0, 0, 0).unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
@@ -6,23 +6,102 @@
 //! MBTiles backend
 
 use ::actix::prelude::*;
-use legeo::message::{GetTile, GetTileResult};
+use legeo::message::{
+    GetInfo, GetInfoResult, GetTile, GetTileResult, PutInfo, PutInfoResult, PutTile, PutTileResult,
+    StopWriting, StopWritingResult,
+};
+use legeo::registry::Registry;
+use legeo::tile::{stat_etag, Tile};
 use legeo::tileconnector::Tileconnector;
+use legeo::tileinfo::TileInfo;
+use legeo::tilesink::Tilesink;
 use legeo::tilesource::Tilesource;
-use log::error;
+use legeo::{Error, Result};
+use legeo_xyz::tile::TileCoord;
 use rusqlite::types::ToSql;
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{Connection, ErrorCode, OpenFlags};
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::HashMap;
-use url::{self, Url};
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// Number of tiles inserted per transaction
+const BATCH_SIZE: usize = 1000;
+/// Wait time for locks of concurrent connections
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Table layout of an MBTiles file
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Layout {
+    /// Single `tiles` table
+    Flat,
+    /// Tile contents in `images` referenced by `map`, keyed by MD5 hash.
+    /// `tiles` is a view joining both tables.
+    Dedup,
+}
 
 pub struct Mbtiles {
     conn: Connection,
+    layout: Layout,
+    /// File size and modification time when opened
+    stat: Option<(u64, SystemTime)>,
+    /// Tiles inserted in the open transaction
+    pending: Cell<usize>,
+}
+
+impl Mbtiles {
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+}
+
+/// Map file access errors to `Error::Io`
+fn open_error(path: &str, err: rusqlite::Error) -> Error {
+    match err {
+        rusqlite::Error::SqliteFailure(ref e, _) if e.code == ErrorCode::CannotOpen => {
+            match fs::metadata(path) {
+                Err(io_err) => Error::Io(io_err),
+                Ok(_) => Error::Io(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    err.to_string(),
+                )),
+            }
+        }
+        rusqlite::Error::SqliteFailure(ref e, _) if e.code == ErrorCode::PermissionDenied => {
+            Error::Io(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                err.to_string(),
+            ))
+        }
+        err => Error::backend(err),
+    }
+}
+
+/// Layout of existing tables
+fn detect_layout(conn: &Connection) -> Result<Option<Layout>> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE name IN ('tiles', 'map')")
+        .map_err(Error::backend)?;
+    let names = stmt
+        .query_map(&[] as &[&dyn ToSql], |rec| rec.get::<_, String>(0))
+        .map_err(Error::backend)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(Error::backend)?;
+    if names.iter().any(|name| name == "map") {
+        Ok(Some(Layout::Dedup))
+    } else if names.iter().any(|name| name == "tiles") {
+        Ok(Some(Layout::Flat))
+    } else {
+        Ok(None)
+    }
 }
 
 impl Tileconnector for Mbtiles {
     /// Create Mbtiles backend
-    fn load(uri: &str) -> Result<Self, url::ParseError> {
+    fn load(uri: &str) -> Result<Self> {
         let uri = Url::parse(uri)?;
         // if (uri.hostname === '.' || uri.hostname == '..') {
         //     uri.pathname = uri.hostname + uri.pathname;
@@ -34,38 +113,268 @@ impl Tileconnector for Mbtiles {
             "ro" => OpenFlags::SQLITE_OPEN_READ_ONLY,
             "rw" => OpenFlags::SQLITE_OPEN_READ_WRITE,
             "rwc" => OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+            // Only supports "ro", "rw", or "rwc" mode.
             _ => {
-                error!(r#"Only supports "ro", "rw", or "rwc" mode."#);
-                return Err(url::ParseError::Overflow);
+                return Err(Error::UnsupportedParameter {
+                    name: "mode".to_string(),
+                    value: mode.to_string(),
+                });
+            }
+        };
+        let schema = match params.get("schema").map(|s| s.as_ref()) {
+            None => None,
+            Some("flat") => Some(Layout::Flat),
+            Some("dedup") => Some(Layout::Dedup),
+            Some(schema) => {
+                return Err(Error::UnsupportedParameter {
+                    name: "schema".to_string(),
+                    value: schema.to_string(),
+                });
+            }
+        };
+        let conn = Connection::open_with_flags(uri.path(), flags)
+            .map_err(|e| open_error(uri.path(), e))?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(Error::backend)?;
+        let layout = match detect_layout(&conn)? {
+            Some(layout) => {
+                // Existing files keep their layout
+                if schema.is_some_and(|schema| schema != layout) {
+                    return Err(Error::UnsupportedParameter {
+                        name: "schema".to_string(),
+                        value: params["schema"].to_string(),
+                    });
+                }
+                layout
             }
+            None => schema.unwrap_or(Layout::Flat),
         };
-        let conn = Connection::open_with_flags(uri.path(), flags).map_err(|e| {
-            error!("Connection error: {}", e);
-            url::ParseError::Overflow
-        })?;
-        Ok(Mbtiles { conn })
+        if mode == "rwc" {
+            let sql = match layout {
+                Layout::Flat => SCHEMA,
+                Layout::Dedup => DEDUP_SCHEMA,
+            };
+            conn.execute_batch(sql).map_err(Error::backend)?;
+        }
+        let stat = fs::metadata(uri.path())
+            .and_then(|meta| Ok((meta.len(), meta.modified()?)))
+            .ok();
+        Ok(Mbtiles {
+            conn,
+            layout,
+            stat,
+            pending: Cell::new(0),
+        })
     }
 }
 
 impl Tilesource for Mbtiles {
-    fn get_tile(&self, z: u8, x: u32, y: u32) -> std::io::Result<Vec<u8>> {
+    /// Tiles of the dedup layout get their content hash as ETag,
+    /// others size and modification time of the file.
+    fn get_tile(&self, z: u8, x: u32, y: u32) -> Result<Option<Tile>> {
         // Flip Y coordinate because MBTiles files are TMS.
-        let y = (1 << z) - 1 - y;
+        let y = match TileCoord::new(z, x, y) {
+            Some(tile) => tile.tms_y(),
+            None => return Ok(None),
+        };
 
-        let mut stmt = self.conn
-            .prepare_cached("SELECT tile_data FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3")
-            .unwrap();
-        let tile: rusqlite::Result<Vec<u8>> =
-            stmt.query_row(&[&z as &ToSql, &x, &y], |rec| rec.get(0));
-        //TODO: Handle "Query returned no rows"
-        let tile =
-            tile.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let sql = match self.layout {
+            Layout::Flat => "SELECT tile_data, NULL FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+            Layout::Dedup => "SELECT images.tile_data, map.tile_id FROM map JOIN images ON images.tile_id = map.tile_id WHERE map.zoom_level = ?1 AND map.tile_column = ?2 AND map.tile_row = ?3",
+        };
+        let mut stmt = self.conn.prepare_cached(sql).map_err(Error::backend)?;
+        let row: rusqlite::Result<(Vec<u8>, Option<String>)> =
+            stmt.query_row(&[&z as &dyn ToSql, &x, &y], |rec| (rec.get(0), rec.get(1)));
+        let (data, tile_id) = match row {
+            Ok(row) => row,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(e) => return Err(Error::backend(e)),
+        };
 
-        // var headers = tiletype.headers(row.tile_data);
-        // headers['Last-Modified'] = new Date(mbtiles._stat.mtime).toUTCString();
-        // headers['ETag'] = mbtiles._stat.size + '-' + Number(mbtiles._stat.mtime);
+        let mut tile = Tile::new(data);
+        if let Some((size, mtime)) = self.stat {
+            tile = tile.with_mtime(mtime).with_etag(stat_etag(size, mtime));
+        }
+        if let Some(tile_id) = tile_id {
+            tile = tile.with_etag(tile_id);
+        }
+        Ok(Some(tile))
+    }
 
-        Ok(tile)
+    /// Read TileJSON entries from `metadata` table.
+    /// Vector layers are read from the `json` entry.
+    fn get_info(&self) -> Result<TileInfo> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, value FROM metadata")
+            .map_err(Error::backend)?;
+        let metadata = stmt
+            .query_map(&[] as &[&dyn ToSql], |rec| {
+                (rec.get::<_, String>(0), rec.get::<_, String>(1))
+            })
+            .map_err(Error::backend)?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()
+            .map_err(Error::backend)?;
+        let vector_layers = metadata
+            .get("json")
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(json).ok())
+            .and_then(|mut json| json.get_mut("vector_layers").map(|layers| layers.take()));
+        Ok(TileInfo {
+            name: metadata.get("name").cloned(),
+            format: metadata.get("format").cloned(),
+            bounds: metadata
+                .get("bounds")
+                .and_then(|bounds| parse_numbers(bounds))
+                .and_then(|n| {
+                    if n.len() == 4 {
+                        Some([n[0], n[1], n[2], n[3]])
+                    } else {
+                        None
+                    }
+                }),
+            center: metadata
+                .get("center")
+                .and_then(|center| parse_numbers(center))
+                .and_then(|n| {
+                    if n.len() == 3 {
+                        Some([n[0], n[1], n[2]])
+                    } else {
+                        None
+                    }
+                }),
+            minzoom: metadata.get("minzoom").and_then(|z| z.trim().parse().ok()),
+            maxzoom: metadata.get("maxzoom").and_then(|z| z.trim().parse().ok()),
+            attribution: metadata.get("attribution").cloned(),
+            vector_layers,
+        })
+    }
+}
+
+/// Parse comma separated list of numbers
+fn parse_numbers(list: &str) -> Option<Vec<f64>> {
+    list.split(',').map(|n| n.trim().parse().ok()).collect()
+}
+
+/// Comma separated list of numbers
+fn format_numbers(numbers: &[f64]) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// https://github.com/mapbox/node-mbtiles/blob/master/lib/schema.sql
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS metadata (name text, value text);
+    CREATE TABLE IF NOT EXISTS tiles (zoom_level integer, tile_column integer, tile_row integer, tile_data blob);
+    CREATE UNIQUE INDEX IF NOT EXISTS name ON metadata (name);
+    CREATE UNIQUE INDEX IF NOT EXISTS tile_index ON tiles (zoom_level, tile_column, tile_row);
+";
+
+const DEDUP_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS map (zoom_level integer, tile_column integer, tile_row integer, tile_id text, grid_id text);
+    CREATE TABLE IF NOT EXISTS images (tile_data blob, tile_id text);
+    CREATE TABLE IF NOT EXISTS metadata (name text, value text);
+    CREATE UNIQUE INDEX IF NOT EXISTS map_index ON map (zoom_level, tile_column, tile_row);
+    CREATE UNIQUE INDEX IF NOT EXISTS images_id ON images (tile_id);
+    CREATE UNIQUE INDEX IF NOT EXISTS name ON metadata (name);
+    CREATE VIEW IF NOT EXISTS tiles AS
+        SELECT map.zoom_level AS zoom_level, map.tile_column AS tile_column, map.tile_row AS tile_row, images.tile_data AS tile_data
+        FROM map JOIN images ON images.tile_id = map.tile_id;
+";
+
+impl Tilesink for Mbtiles {
+    fn single_writer() -> bool {
+        true
+    }
+
+    /// Insert tile. Tiles are written in transactions of `BATCH_SIZE` tiles,
+    /// the last transaction is committed by `stop_writing`.
+    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<()> {
+        // Flip Y coordinate because MBTiles files are TMS.
+        let y = TileCoord::new(z, x, y)
+            .ok_or_else(|| Error::backend(format!("Invalid tile {}/{}/{}", z, x, y)))?
+            .tms_y();
+
+        // A transaction stays open after failed inserts
+        if self.conn.is_autocommit() {
+            self.conn.execute_batch("BEGIN").map_err(Error::backend)?;
+        }
+        match self.layout {
+            Layout::Flat => {
+                let mut stmt = self.conn
+                    .prepare_cached("INSERT OR REPLACE INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)")
+                    .map_err(Error::backend)?;
+                stmt.execute(&[&z as &dyn ToSql, &x, &y, &data])
+                    .map_err(Error::backend)?;
+            }
+            Layout::Dedup => {
+                let tile_id = format!("{:x}", md5::compute(&data));
+                let mut stmt = self
+                    .conn
+                    .prepare_cached(
+                        "INSERT OR IGNORE INTO images (tile_id, tile_data) VALUES (?1, ?2)",
+                    )
+                    .map_err(Error::backend)?;
+                stmt.execute(&[&tile_id as &dyn ToSql, &data])
+                    .map_err(Error::backend)?;
+                let mut stmt = self.conn
+                    .prepare_cached("INSERT OR REPLACE INTO map (zoom_level, tile_column, tile_row, tile_id) VALUES (?1, ?2, ?3, ?4)")
+                    .map_err(Error::backend)?;
+                stmt.execute(&[&z as &dyn ToSql, &x, &y, &tile_id])
+                    .map_err(Error::backend)?;
+            }
+        }
+        self.pending.set(self.pending.get() + 1);
+        if self.pending.get() >= BATCH_SIZE {
+            self.stop_writing()?;
+        }
+        Ok(())
+    }
+
+    /// Write TileJSON entries into `metadata` table
+    fn put_info(&self, info: &TileInfo) -> Result<()> {
+        let metadata = vec![
+            ("name", info.name.clone()),
+            ("format", info.format.clone()),
+            ("bounds", info.bounds.map(|b| format_numbers(&b))),
+            ("center", info.center.map(|c| format_numbers(&c))),
+            ("minzoom", info.minzoom.map(|z| z.to_string())),
+            ("maxzoom", info.maxzoom.map(|z| z.to_string())),
+            ("attribution", info.attribution.clone()),
+            (
+                "json",
+                info.vector_layers
+                    .as_ref()
+                    .map(|layers| serde_json::json!({ "vector_layers": layers }).to_string()),
+            ),
+        ];
+        let mut stmt = self
+            .conn
+            .prepare_cached("INSERT OR REPLACE INTO metadata (name, value) VALUES (?1, ?2)")
+            .map_err(Error::backend)?;
+        for (name, value) in metadata {
+            if let Some(value) = value {
+                stmt.execute(&[&name as &dyn ToSql, &value])
+                    .map_err(Error::backend)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn stop_writing(&self) -> Result<()> {
+        if !self.conn.is_autocommit() {
+            self.pending.set(0);
+            self.conn.execute_batch("COMMIT").map_err(Error::backend)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Mbtiles {
+    fn drop(&mut self) {
+        // Commit pending tiles of an unfinished copy
+        let _ = self.stop_writing();
     }
 }
 
@@ -80,3 +389,198 @@ impl Handler<GetTile> for Mbtiles {
         self.get_tile(msg.z, msg.x, msg.y)
     }
 }
+
+impl Handler<GetInfo> for Mbtiles {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        self.get_info()
+    }
+}
+
+impl Handler<PutTile> for Mbtiles {
+    type Result = PutTileResult;
+
+    fn handle(&mut self, msg: PutTile, _: &mut Context<Self>) -> Self::Result {
+        self.put_tile(msg.z, msg.x, msg.y, msg.data)
+    }
+}
+
+impl Handler<PutInfo> for Mbtiles {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        self.put_info(&msg.info)
+    }
+}
+
+impl Handler<StopWriting> for Mbtiles {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        self.stop_writing()
+    }
+}
+
+/// Register `mbtiles://` source and sink
+pub fn register(registry: &mut Registry) {
+    registry.register_source::<Mbtiles>("mbtiles");
+    registry.register_sink::<Mbtiles>("mbtiles");
+}
+
+#[test]
+fn test_unsupported_mode() {
+    match Mbtiles::load("mbtiles:///tmp/legeo.mbtiles?mode=rx") {
+        Err(Error::UnsupportedParameter { name, value }) => {
+            assert_eq!(name, "mode");
+            assert_eq!(value, "rx");
+        }
+        _ => panic!("UnsupportedParameter expected"),
+    }
+}
+
+#[test]
+fn test_missing_file() {
+    match Mbtiles::load("mbtiles:///tmp/legeo_missing/missing.mbtiles?mode=ro") {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+        _ => panic!("Io error expected"),
+    }
+}
+
+#[cfg(test)]
+fn count(conn: &Connection, table: &str) -> u32 {
+    conn.query_row(
+        &format!("SELECT count(*) FROM {}", table),
+        &[] as &[&dyn ToSql],
+        |rec| rec.get(0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_write_tiles() {
+    let _ = std::fs::remove_file("/tmp/legeo_write.mbtiles");
+    let sink = Mbtiles::load("mbtiles:///tmp/legeo_write.mbtiles").unwrap();
+    for x in 0..4 {
+        for y in 0..4 {
+            sink.put_tile(2, x, y, format!("2/{}/{}", x, y).into_bytes())
+                .unwrap();
+        }
+    }
+    sink.put_tile(2, 1, 0, b"updated".to_vec()).unwrap();
+    sink.stop_writing().unwrap();
+
+    let source = Mbtiles::load("mbtiles:///tmp/legeo_write.mbtiles?mode=ro").unwrap();
+    assert_eq!(
+        source.get_tile(2, 3, 1).unwrap().map(|t| t.data),
+        Some(b"2/3/1".to_vec())
+    );
+    assert_eq!(
+        source.get_tile(2, 1, 0).unwrap().map(|t| t.data),
+        Some(b"updated".to_vec())
+    );
+    let meta = std::fs::metadata("/tmp/legeo_write.mbtiles").unwrap();
+    let tile = source.get_tile(2, 1, 0).unwrap().unwrap();
+    assert_eq!(tile.mtime, Some(meta.modified().unwrap()));
+    assert_eq!(
+        tile.etag,
+        Some(stat_etag(meta.len(), meta.modified().unwrap()))
+    );
+    assert_eq!(source.get_tile(3, 0, 0).unwrap(), None);
+    // Stored as TMS row
+    let row: u32 = source
+        .conn
+        .query_row(
+            "SELECT tile_row FROM tiles WHERE tile_data = ?1",
+            &[&b"2/3/1".to_vec() as &dyn ToSql],
+            |rec| rec.get(0),
+        )
+        .unwrap();
+    assert_eq!(row, 2);
+}
+
+#[test]
+fn test_write_after_error() {
+    let _ = std::fs::remove_file("/tmp/legeo_write_error.mbtiles");
+    let sink = Mbtiles::load("mbtiles:///tmp/legeo_write_error.mbtiles").unwrap();
+    sink.conn
+        .execute_batch("ALTER TABLE tiles RENAME TO tiles_tmp")
+        .unwrap();
+    assert!(sink.put_tile(0, 0, 0, b"0/0/0".to_vec()).is_err());
+    sink.conn
+        .execute_batch("ALTER TABLE tiles_tmp RENAME TO tiles")
+        .unwrap();
+    sink.put_tile(0, 0, 0, b"0/0/0".to_vec()).unwrap();
+    sink.stop_writing().unwrap();
+    assert!(sink.conn.is_autocommit());
+    assert_eq!(count(&sink.conn, "tiles"), 1);
+}
+
+#[test]
+fn test_dedup_tiles() {
+    let _ = std::fs::remove_file("/tmp/legeo_dedup.mbtiles");
+    let sink = Mbtiles::load("mbtiles:///tmp/legeo_dedup.mbtiles?schema=dedup").unwrap();
+    assert_eq!(sink.layout(), Layout::Dedup);
+    for x in 0..4 {
+        for y in 0..4 {
+            let data = if x == 3 && y == 1 { "land" } else { "ocean" };
+            sink.put_tile(2, x, y, data.as_bytes().to_vec()).unwrap();
+        }
+    }
+    sink.stop_writing().unwrap();
+    assert_eq!(count(&sink.conn, "map"), 16);
+    assert_eq!(count(&sink.conn, "images"), 2);
+    drop(sink);
+
+    // Layout of existing file is detected
+    let source = Mbtiles::load("mbtiles:///tmp/legeo_dedup.mbtiles?mode=ro").unwrap();
+    assert_eq!(source.layout(), Layout::Dedup);
+    assert_eq!(
+        source.get_tile(2, 3, 1).unwrap().map(|t| t.data),
+        Some(b"land".to_vec())
+    );
+    assert_eq!(
+        source.get_tile(2, 0, 0).unwrap().map(|t| t.data),
+        Some(b"ocean".to_vec())
+    );
+    let tile = source.get_tile(2, 0, 0).unwrap().unwrap();
+    assert_eq!(tile.etag, Some(format!("{:x}", md5::compute(b"ocean"))));
+    assert!(tile.mtime.is_some());
+    assert_eq!(source.get_tile(3, 0, 0).unwrap(), None);
+
+    match Mbtiles::load("mbtiles:///tmp/legeo_dedup.mbtiles?schema=flat") {
+        Err(Error::UnsupportedParameter { name, .. }) => assert_eq!(name, "schema"),
+        _ => panic!("UnsupportedParameter expected"),
+    }
+}
+
+#[test]
+fn test_info() {
+    let _ = std::fs::remove_file("/tmp/legeo_info.mbtiles");
+    let sink = Mbtiles::load("mbtiles:///tmp/legeo_info.mbtiles").unwrap();
+    assert_eq!(sink.get_info().unwrap(), TileInfo::default());
+    let info = TileInfo {
+        name: Some("legeo".to_string()),
+        format: Some("pbf".to_string()),
+        bounds: Some([5.9, 45.8, 10.5, 47.8]),
+        center: Some([8.2, 46.8, 7.0]),
+        minzoom: Some(0),
+        maxzoom: Some(14),
+        attribution: Some("&copy; OpenStreetMap contributors".to_string()),
+        vector_layers: Some(serde_json::json!([{"id": "water", "fields": {}}])),
+    };
+    sink.put_info(&info).unwrap();
+    let metadata: String = sink
+        .conn
+        .query_row(
+            "SELECT value FROM metadata WHERE name = 'bounds'",
+            &[] as &[&dyn ToSql],
+            |rec| rec.get(0),
+        )
+        .unwrap();
+    assert_eq!(metadata, "5.9,45.8,10.5,47.8");
+    drop(sink);
+
+    let source = Mbtiles::load("mbtiles:///tmp/legeo_info.mbtiles?mode=ro").unwrap();
+    assert_eq!(source.get_info().unwrap(), info);
+}
@@ -6,21 +6,24 @@
 //! Noop sink
 
 use ::actix::prelude::*;
-use legeo::message::{PutTile, PutTileResult};
+use legeo::message::{
+    PutInfo, PutInfoResult, PutTile, PutTileResult, StopWriting, StopWritingResult,
+};
+use legeo::registry::Registry;
 use legeo::tileconnector::Tileconnector;
 use legeo::tilesink::Tilesink;
-use url;
+use legeo::Result;
 
 pub struct NullSink {}
 
 impl Tileconnector for NullSink {
-    fn load(_uri: &str) -> Result<Self, url::ParseError> {
+    fn load(_uri: &str) -> Result<Self> {
         Ok(NullSink {})
     }
 }
 
 impl Tilesink for NullSink {
-    fn put_tile(&self, _z: u8, _x: u32, _y: u32, _data: Vec<u8>) -> std::io::Result<()> {
+    fn put_tile(&self, _z: u8, _x: u32, _y: u32, _data: Vec<u8>) -> Result<()> {
         Ok(())
     }
 }
@@ -39,6 +42,29 @@ impl Handler<PutTile> for NullSink {
     }
 }
 
+// Handler for `PutInfo` message
+impl Handler<PutInfo> for NullSink {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, _msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        Ok(())
+    }
+}
+
+// Handler for `StopWriting` message
+impl Handler<StopWriting> for NullSink {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        Ok(())
+    }
+}
+
+/// Register `null://` sink
+pub fn register(registry: &mut Registry) {
+    registry.register_sink::<NullSink>("null");
+}
+
 // var Null = function(uri, callback) {
 //   return setImmediate(callback, null, this);
 // };
@@ -0,0 +1,399 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! PMTiles v3 header, tile ids and directories
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Write};
+
+//  From https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md
+//
+// A PMTiles archive is a single-file archive of tiles:
+// Header (127 bytes), Root Directory, JSON Metadata, Leaf Directories, Tile Data.
+// The header plus root directory must fit in the first 16,384 bytes.
+
+pub const HEADER_LEN: usize = 127;
+
+/// Max length of header and root directory
+pub const ROOT_MAX_LEN: usize = 16_384;
+
+/// Compression types
+pub mod compression {
+    pub const UNKNOWN: u8 = 0;
+    pub const NONE: u8 = 1;
+    pub const GZIP: u8 = 2;
+    pub const BROTLI: u8 = 3;
+    pub const ZSTD: u8 = 4;
+}
+
+/// Tile types
+pub mod tile_type {
+    pub const UNKNOWN: u8 = 0;
+    pub const MVT: u8 = 1;
+    pub const PNG: u8 = 2;
+    pub const JPEG: u8 = 3;
+    pub const WEBP: u8 = 4;
+    pub const AVIF: u8 = 5;
+}
+
+/// PMTiles v3 header
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct Header {
+    pub root_offset: u64,
+    pub root_length: u64,
+    pub metadata_offset: u64,
+    pub metadata_length: u64,
+    pub leaf_offset: u64,
+    pub leaf_length: u64,
+    pub data_offset: u64,
+    pub data_length: u64,
+    pub addressed_tiles: u64,
+    pub tile_entries: u64,
+    pub tile_contents: u64,
+    pub clustered: bool,
+    pub internal_compression: u8,
+    pub tile_compression: u8,
+    pub tile_type: u8,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    /// Bounds in degrees `[w, s, e, n]`
+    pub bounds: [f64; 4],
+    pub center_zoom: u8,
+    /// Center in degrees `[lon, lat]`
+    pub center: [f64; 2],
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn u64_at(buf: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn e7_at(buf: &[u8], pos: usize) -> f64 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buf[pos..pos + 4]);
+    f64::from(i32::from_le_bytes(bytes)) / 10_000_000.0
+}
+
+fn e7(degrees: f64) -> [u8; 4] {
+    ((degrees * 10_000_000.0).round() as i32).to_le_bytes()
+}
+
+impl Header {
+    pub fn read(buf: &[u8]) -> io::Result<Header> {
+        if buf.len() < HEADER_LEN || &buf[0..7] != b"PMTiles" {
+            return Err(invalid_data("Not a PMTiles archive"));
+        }
+        if buf[7] != 3 {
+            return Err(invalid_data("Unsupported PMTiles version"));
+        }
+        Ok(Header {
+            root_offset: u64_at(buf, 8),
+            root_length: u64_at(buf, 16),
+            metadata_offset: u64_at(buf, 24),
+            metadata_length: u64_at(buf, 32),
+            leaf_offset: u64_at(buf, 40),
+            leaf_length: u64_at(buf, 48),
+            data_offset: u64_at(buf, 56),
+            data_length: u64_at(buf, 64),
+            addressed_tiles: u64_at(buf, 72),
+            tile_entries: u64_at(buf, 80),
+            tile_contents: u64_at(buf, 88),
+            clustered: buf[96] == 1,
+            internal_compression: buf[97],
+            tile_compression: buf[98],
+            tile_type: buf[99],
+            min_zoom: buf[100],
+            max_zoom: buf[101],
+            bounds: [
+                e7_at(buf, 102),
+                e7_at(buf, 106),
+                e7_at(buf, 110),
+                e7_at(buf, 114),
+            ],
+            center_zoom: buf[118],
+            center: [e7_at(buf, 119), e7_at(buf, 123)],
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN);
+        buf.extend_from_slice(b"PMTiles");
+        buf.push(3);
+        for value in &[
+            self.root_offset,
+            self.root_length,
+            self.metadata_offset,
+            self.metadata_length,
+            self.leaf_offset,
+            self.leaf_length,
+            self.data_offset,
+            self.data_length,
+            self.addressed_tiles,
+            self.tile_entries,
+            self.tile_contents,
+        ] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        buf.push(self.clustered as u8);
+        buf.push(self.internal_compression);
+        buf.push(self.tile_compression);
+        buf.push(self.tile_type);
+        buf.push(self.min_zoom);
+        buf.push(self.max_zoom);
+        for degrees in &self.bounds {
+            buf.extend_from_slice(&e7(*degrees));
+        }
+        buf.push(self.center_zoom);
+        buf.extend_from_slice(&e7(self.center[0]));
+        buf.extend_from_slice(&e7(self.center[1]));
+        buf
+    }
+}
+
+/// Directory entry. A run length of 0 points to a leaf directory.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entry {
+    pub tile_id: u64,
+    pub offset: u64,
+    pub length: u32,
+    pub run_length: u32,
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn read_varint(buf: &mut &[u8]) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf
+            .split_first()
+            .ok_or_else(|| invalid_data("Truncated directory"))?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("Invalid varint"))
+}
+
+/// Serialize directory entries sorted by tile id (uncompressed)
+pub fn serialize_directory(entries: &[Entry]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_varint(&mut buf, entries.len() as u64);
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut buf, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut buf, u64::from(entry.run_length));
+    }
+    for entry in entries {
+        write_varint(&mut buf, u64::from(entry.length));
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let contiguous =
+            i > 0 && entry.offset == entries[i - 1].offset + u64::from(entries[i - 1].length);
+        if contiguous {
+            write_varint(&mut buf, 0);
+        } else {
+            write_varint(&mut buf, entry.offset + 1);
+        }
+    }
+    buf
+}
+
+/// Deserialize uncompressed directory
+pub fn deserialize_directory(mut buf: &[u8]) -> io::Result<Vec<Entry>> {
+    let buf = &mut buf;
+    let count = read_varint(buf)? as usize;
+    let mut entries = Vec::with_capacity(count.min(buf.len()));
+    let mut last_id = 0;
+    for _ in 0..count {
+        last_id += read_varint(buf)?;
+        entries.push(Entry {
+            tile_id: last_id,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        });
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(buf)? as u32;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(buf)? as u32;
+    }
+    for i in 0..entries.len() {
+        let offset = read_varint(buf)?;
+        entries[i].offset = if offset == 0 && i > 0 {
+            entries[i - 1].offset + u64::from(entries[i - 1].length)
+        } else {
+            offset.saturating_sub(1)
+        };
+    }
+    Ok(entries)
+}
+
+/// Entry containing tile id. Entries have to be sorted by tile id.
+pub fn find_tile(entries: &[Entry], tile_id: u64) -> Option<&Entry> {
+    let pos = match entries.binary_search_by_key(&tile_id, |entry| entry.tile_id) {
+        Ok(pos) => pos,
+        Err(0) => return None,
+        Err(pos) => pos - 1,
+    };
+    let entry = &entries[pos];
+    if entry.run_length == 0 || tile_id < entry.tile_id + u64::from(entry.run_length) {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+pub fn compress(data: &[u8], compression: u8) -> io::Result<Vec<u8>> {
+    match compression {
+        compression::NONE => Ok(data.to_vec()),
+        compression::GZIP => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        _ => Err(invalid_data("Unsupported compression")),
+    }
+}
+
+pub fn decompress(data: &[u8], compression: u8) -> io::Result<Vec<u8>> {
+    match compression {
+        compression::NONE => Ok(data.to_vec()),
+        compression::GZIP => {
+            let mut buf = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut buf)?;
+            Ok(buf)
+        }
+        _ => Err(invalid_data("Unsupported compression")),
+    }
+}
+
+/// Compressed root and leaf directories for sorted `entries`.
+/// Entries are split into leaves until the root directory fits into the first 16K.
+pub fn build_directories(entries: &[Entry], compression: u8) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    let root = compress(&serialize_directory(entries), compression)?;
+    if root.len() <= ROOT_MAX_LEN - HEADER_LEN {
+        return Ok((root, Vec::new()));
+    }
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = compress(&serialize_directory(chunk), compression)?;
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u32,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+        let root = compress(&serialize_directory(&root_entries), compression)?;
+        if root.len() <= ROOT_MAX_LEN - HEADER_LEN {
+            return Ok((root, leaves));
+        }
+        leaf_size *= 2;
+    }
+}
+
+#[test]
+fn test_directory() {
+    let entries = vec![
+        Entry {
+            tile_id: 0,
+            offset: 0,
+            length: 10,
+            run_length: 1,
+        },
+        Entry {
+            tile_id: 1,
+            offset: 10,
+            length: 5,
+            run_length: 3,
+        },
+        Entry {
+            tile_id: 9,
+            offset: 0,
+            length: 10,
+            run_length: 1,
+        },
+    ];
+    let buf = serialize_directory(&entries);
+    assert_eq!(buf, vec![3, 0, 1, 8, 1, 3, 1, 10, 5, 10, 1, 0, 1]);
+    assert_eq!(deserialize_directory(&buf).unwrap(), entries);
+
+    assert_eq!(find_tile(&entries, 3).unwrap().tile_id, 1);
+    assert_eq!(find_tile(&entries, 4), None);
+    assert_eq!(find_tile(&entries, 9).unwrap().offset, 0);
+    assert_eq!(find_tile(&entries, 10), None);
+}
+
+#[test]
+fn test_leaf_directories() {
+    let entries = (0..100_000)
+        .map(|i| Entry {
+            tile_id: i * 2,
+            offset: i * 1000,
+            length: 1000 + (i % 7) as u32,
+            run_length: 1,
+        })
+        .collect::<Vec<_>>();
+    let (root, leaves) = build_directories(&entries, compression::GZIP).unwrap();
+    assert!(root.len() <= ROOT_MAX_LEN - HEADER_LEN);
+    let root = deserialize_directory(&decompress(&root, compression::GZIP).unwrap()).unwrap();
+    let leaf = find_tile(&root, 2 * 5000 + 1).unwrap();
+    assert_eq!(leaf.run_length, 0);
+    let start = leaf.offset as usize;
+    let leaf_entries = deserialize_directory(
+        &decompress(
+            &leaves[start..start + leaf.length as usize],
+            compression::GZIP,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(find_tile(&leaf_entries, 2 * 5000), Some(&entries[5000]));
+}
+
+#[test]
+fn test_header() {
+    let header = Header {
+        root_offset: 127,
+        root_length: 25,
+        data_offset: 1000,
+        addressed_tiles: 3,
+        clustered: true,
+        internal_compression: compression::GZIP,
+        tile_type: tile_type::PNG,
+        max_zoom: 14,
+        bounds: [-180.0, -85.0511, 180.0, 85.0511],
+        center_zoom: 3,
+        center: [8.5, 47.25],
+        ..Default::default()
+    };
+    let buf = header.to_bytes();
+    assert_eq!(buf.len(), HEADER_LEN);
+    assert_eq!(Header::read(&buf).unwrap(), header);
+    assert!(Header::read(b"MBTiles").is_err());
+}
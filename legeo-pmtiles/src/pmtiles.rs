@@ -0,0 +1,590 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! PMTiles backend
+
+use crate::directory::{
+    self, build_directories, compress, decompress, deserialize_directory, find_tile, Entry, Header,
+    HEADER_LEN,
+};
+use ::actix::prelude::*;
+use legeo::message::{
+    GetInfo, GetInfoResult, GetTile, GetTileResult, PutInfo, PutInfoResult, PutTile, PutTileResult,
+    StopWriting, StopWritingResult,
+};
+use legeo::registry::Registry;
+use legeo::tile::{stat_etag, Tile};
+use legeo::tileconnector::Tileconnector;
+use legeo::tileinfo::TileInfo;
+use legeo::tilesink::Tilesink;
+use legeo::tilesource::Tilesource;
+use legeo::tiletype::{self, Compression, TileFormat, TileType};
+use legeo::Result;
+use legeo_xyz::tile::TileCoord;
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use url::Url;
+
+/// Maximal depth of leaf directories
+const MAX_DEPTH: usize = 3;
+
+/// Reads and writes a PMTiles v3 archive.
+///
+/// Tiles are collected in a temporary file and the archive is written by `stop_writing`,
+/// so an archive can only be written by a single sink.
+pub struct Pmtiles {
+    path: PathBuf,
+    /// Archive opened on first read
+    reader: RefCell<Option<Reader>>,
+    /// Pending writes
+    writer: RefCell<Option<Writer>>,
+}
+
+struct Reader {
+    file: File,
+    header: Header,
+    root: Vec<Entry>,
+    /// File size and modification time when opened
+    stat: (u64, SystemTime),
+}
+
+struct Writer {
+    tmp_path: PathBuf,
+    tmp: BufWriter<File>,
+    tmp_len: u64,
+    /// Offset and length of tile contents by MD5 hash
+    contents: HashMap<[u8; 16], (u64, u32)>,
+    /// Tile id, offset and length in temporary file, in order of writes
+    tiles: Vec<(u64, u64, u32)>,
+    /// Type of first tile
+    tiletype: Option<TileType>,
+    info: Option<TileInfo>,
+}
+
+fn read_at(file: &mut File, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; length as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+impl Reader {
+    fn open(path: &Path) -> Result<Reader> {
+        let mut file = File::open(path)?;
+        let meta = file.metadata()?;
+        let header = Header::read(&read_at(&mut file, 0, HEADER_LEN as u64)?)?;
+        let root = read_at(&mut file, header.root_offset, header.root_length)?;
+        let root = deserialize_directory(&decompress(&root, header.internal_compression)?)?;
+        Ok(Reader {
+            file,
+            header,
+            root,
+            stat: (meta.len(), meta.modified()?),
+        })
+    }
+
+    fn get_tile(&mut self, z: u8, x: u32, y: u32) -> Result<Option<Vec<u8>>> {
+        let id = match TileCoord::new(z, x, y) {
+            Some(tile) => tile.hilbert_id(),
+            None => return Ok(None),
+        };
+        let mut entry = match find_tile(&self.root, id) {
+            Some(entry) => *entry,
+            None => return Ok(None),
+        };
+        for _ in 0..MAX_DEPTH {
+            if entry.run_length > 0 {
+                let data = read_at(
+                    &mut self.file,
+                    self.header.data_offset + entry.offset,
+                    u64::from(entry.length),
+                )?;
+                return Ok(Some(data));
+            }
+            let leaf = read_at(
+                &mut self.file,
+                self.header.leaf_offset + entry.offset,
+                u64::from(entry.length),
+            )?;
+            let leaf =
+                deserialize_directory(&decompress(&leaf, self.header.internal_compression)?)?;
+            entry = match find_tile(&leaf, id) {
+                Some(entry) => *entry,
+                None => return Ok(None),
+            };
+        }
+        Err(io::Error::new(io::ErrorKind::InvalidData, "Leaf directories too deep").into())
+    }
+
+    /// Metadata JSON with bounds, center, zoom levels and format from header
+    fn get_info(&mut self) -> Result<TileInfo> {
+        let header = self.header.clone();
+        let metadata = read_at(
+            &mut self.file,
+            header.metadata_offset,
+            header.metadata_length,
+        )?;
+        let mut info = if metadata.is_empty() {
+            TileInfo::default()
+        } else {
+            let json = decompress(&metadata, header.internal_compression)?;
+            TileInfo::from_tilejson(&String::from_utf8_lossy(&json))?
+        };
+        let [w, s, e, n] = header.bounds;
+        info.bounds = info.bounds.or(Some([w, s, e, n]));
+        info.center = info.center.or(Some([
+            header.center[0],
+            header.center[1],
+            f64::from(header.center_zoom),
+        ]));
+        info.minzoom = info.minzoom.or(Some(header.min_zoom));
+        info.maxzoom = info.maxzoom.or(Some(header.max_zoom));
+        info.format = info
+            .format
+            .or_else(|| format_of(header.tile_type).map(|format| format.name().to_string()));
+        Ok(info)
+    }
+}
+
+/// Tile format of PMTiles tile type
+fn format_of(tile_type: u8) -> Option<TileFormat> {
+    match tile_type {
+        directory::tile_type::MVT => Some(TileFormat::Pbf),
+        directory::tile_type::PNG => Some(TileFormat::Png),
+        directory::tile_type::JPEG => Some(TileFormat::Jpeg),
+        directory::tile_type::WEBP => Some(TileFormat::Webp),
+        _ => None,
+    }
+}
+
+/// PMTiles tile type of tile format
+fn tile_type_of(format: TileFormat) -> u8 {
+    match format {
+        TileFormat::Pbf => directory::tile_type::MVT,
+        TileFormat::Png => directory::tile_type::PNG,
+        TileFormat::Jpeg => directory::tile_type::JPEG,
+        TileFormat::Webp => directory::tile_type::WEBP,
+        TileFormat::Gif | TileFormat::Unknown => directory::tile_type::UNKNOWN,
+    }
+}
+
+/// PMTiles compression of tile compression
+fn compression_of(compression: Compression) -> u8 {
+    match compression {
+        Compression::None => directory::compression::NONE,
+        Compression::Gzip => directory::compression::GZIP,
+        Compression::Brotli => directory::compression::BROTLI,
+        Compression::Zstd => directory::compression::ZSTD,
+        Compression::Zlib => directory::compression::UNKNOWN,
+    }
+}
+
+impl Writer {
+    fn create(path: &Path) -> Result<Writer> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        // Fail if another sink is writing the same archive
+        let tmp = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::AlreadyExists => io::Error::new(
+                    e.kind(),
+                    format!(
+                        "`{}` exists. Remove it if no other copy is writing this archive.",
+                        tmp_path.display()
+                    ),
+                ),
+                _ => e,
+            })?;
+        Ok(Writer {
+            tmp_path,
+            tmp: BufWriter::new(tmp),
+            tmp_len: 0,
+            contents: HashMap::new(),
+            tiles: Vec::new(),
+            tiletype: None,
+            info: None,
+        })
+    }
+
+    /// Append tile data to temporary file, unless already written
+    fn put_tile(&mut self, z: u8, x: u32, y: u32, data: &[u8]) -> io::Result<()> {
+        let id = TileCoord::new(z, x, y)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid tile {}/{}/{}", z, x, y),
+                )
+            })?
+            .hilbert_id();
+        let hash = md5::compute(data).0;
+        let (offset, length) = match self.contents.get(&hash) {
+            Some(content) => *content,
+            None => {
+                let content = (self.tmp_len, data.len() as u32);
+                self.tmp.write_all(data)?;
+                self.tmp_len += data.len() as u64;
+                self.contents.insert(hash, content);
+                content
+            }
+        };
+        if self.tiletype.is_none() {
+            self.tiletype = Some(tiletype::detect(data));
+        }
+        self.tiles.push((id, offset, length));
+        Ok(())
+    }
+
+    /// Write archive with tiles clustered in tile id order
+    fn finish(mut self, path: &Path) -> io::Result<()> {
+        // Keep last write of each tile
+        self.tiles.reverse();
+        self.tiles.sort_by_key(|tile| tile.0);
+        self.tiles.dedup_by_key(|tile| tile.0);
+
+        // Assign data offsets and merge runs of identical tiles
+        let mut data_offsets: HashMap<u64, u64> = HashMap::new();
+        let mut contents = Vec::new();
+        let mut data_length = 0;
+        let mut entries: Vec<Entry> = Vec::new();
+        for &(id, tmp_offset, length) in &self.tiles {
+            let offset = *data_offsets.entry(tmp_offset).or_insert_with(|| {
+                contents.push((tmp_offset, length));
+                data_length += u64::from(length);
+                data_length - u64::from(length)
+            });
+            if let Some(last) = entries.last_mut() {
+                if last.offset == offset && last.tile_id + u64::from(last.run_length) == id {
+                    last.run_length += 1;
+                    continue;
+                }
+            }
+            entries.push(Entry {
+                tile_id: id,
+                offset,
+                length,
+                run_length: 1,
+            });
+        }
+
+        let internal_compression = directory::compression::GZIP;
+        let (root, leaves) = build_directories(&entries, internal_compression)?;
+        let info = self.info.take().unwrap_or_default();
+        let metadata = compress(&serde_json::to_vec(&info)?, internal_compression)?;
+        let tiletype = self.tiletype.unwrap_or(TileType {
+            format: TileFormat::Unknown,
+            compression: Compression::None,
+        });
+        let format = info
+            .format
+            .as_ref()
+            .map(|name| TileFormat::from_name(name))
+            .unwrap_or(tiletype.format);
+        let zooms = (
+            entries
+                .first()
+                .and_then(|e| TileCoord::from_hilbert_id(e.tile_id))
+                .map(|tile| tile.z),
+            entries
+                .last()
+                .and_then(|e| TileCoord::from_hilbert_id(e.tile_id + u64::from(e.run_length) - 1))
+                .map(|tile| tile.z),
+        );
+        let bounds = info
+            .bounds
+            .unwrap_or([-180.0, -85.051_129, 180.0, 85.051_129]);
+        let min_zoom = zooms.0.unwrap_or(0);
+        let center = info.center.unwrap_or([
+            (bounds[0] + bounds[2]) / 2.0,
+            (bounds[1] + bounds[3]) / 2.0,
+            f64::from(min_zoom),
+        ]);
+        let header = Header {
+            root_offset: HEADER_LEN as u64,
+            root_length: root.len() as u64,
+            metadata_offset: (HEADER_LEN + root.len()) as u64,
+            metadata_length: metadata.len() as u64,
+            leaf_offset: (HEADER_LEN + root.len() + metadata.len()) as u64,
+            leaf_length: leaves.len() as u64,
+            data_offset: (HEADER_LEN + root.len() + metadata.len() + leaves.len()) as u64,
+            data_length,
+            addressed_tiles: self.tiles.len() as u64,
+            tile_entries: entries.len() as u64,
+            tile_contents: contents.len() as u64,
+            clustered: true,
+            internal_compression,
+            tile_compression: compression_of(tiletype.compression),
+            tile_type: tile_type_of(format),
+            min_zoom,
+            max_zoom: zooms.1.unwrap_or(0),
+            bounds,
+            center_zoom: center[2] as u8,
+            center: [center[0], center[1]],
+        };
+
+        let mut tmp = self.tmp.into_inner().map_err(|e| e.into_error())?;
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&header.to_bytes())?;
+        out.write_all(&root)?;
+        out.write_all(&metadata)?;
+        out.write_all(&leaves)?;
+        for (offset, length) in contents {
+            out.write_all(&read_at(&mut tmp, offset, u64::from(length))?)?;
+        }
+        out.flush()?;
+        drop(tmp);
+        fs::remove_file(&self.tmp_path)
+    }
+}
+
+impl Pmtiles {
+    fn reader(&self) -> Result<RefMut<'_, Reader>> {
+        let mut reader = self.reader.borrow_mut();
+        if reader.is_none() {
+            *reader = Some(Reader::open(&self.path)?);
+        }
+        Ok(RefMut::map(reader, |r| r.as_mut().unwrap()))
+    }
+
+    fn writer(&self) -> Result<RefMut<'_, Writer>> {
+        let mut writer = self.writer.borrow_mut();
+        if writer.is_none() {
+            *writer = Some(Writer::create(&self.path)?);
+        }
+        Ok(RefMut::map(writer, |w| w.as_mut().unwrap()))
+    }
+}
+
+impl Tileconnector for Pmtiles {
+    /// Create PMTiles backend. The archive is opened on first read.
+    fn load(uri: &str) -> Result<Self> {
+        let uri = Url::parse(uri)?;
+        Ok(Pmtiles {
+            path: PathBuf::from(uri.path()),
+            reader: RefCell::new(None),
+            writer: RefCell::new(None),
+        })
+    }
+}
+
+impl Tilesource for Pmtiles {
+    /// Tile with modification time and ETag of the archive
+    fn get_tile(&self, z: u8, x: u32, y: u32) -> Result<Option<Tile>> {
+        let mut reader = self.reader()?;
+        let (size, mtime) = reader.stat;
+        Ok(reader.get_tile(z, x, y)?.map(|data| {
+            Tile::new(data)
+                .with_mtime(mtime)
+                .with_etag(stat_etag(size, mtime))
+        }))
+    }
+
+    /// Metadata JSON completed with header fields
+    fn get_info(&self) -> Result<TileInfo> {
+        self.reader()?.get_info()
+    }
+}
+
+impl Tilesink for Pmtiles {
+    fn single_writer() -> bool {
+        true
+    }
+
+    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<()> {
+        self.writer()?.put_tile(z, x, y, &data)?;
+        Ok(())
+    }
+
+    /// Info is written as metadata JSON. Bounds and center are also stored in the header.
+    fn put_info(&self, info: &TileInfo) -> Result<()> {
+        self.writer()?.info = Some(info.clone());
+        Ok(())
+    }
+
+    /// Write archive
+    fn stop_writing(&self) -> Result<()> {
+        if let Some(writer) = self.writer.borrow_mut().take() {
+            writer.finish(&self.path)?;
+            // Reopen updated archive on next read
+            *self.reader.borrow_mut() = None;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Pmtiles {
+    fn drop(&mut self) {
+        // Write tiles of an unfinished copy
+        let _ = self.stop_writing();
+    }
+}
+
+impl Actor for Pmtiles {
+    type Context = Context<Self>;
+}
+
+impl Handler<GetTile> for Pmtiles {
+    type Result = GetTileResult;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        self.get_tile(msg.z, msg.x, msg.y)
+    }
+}
+
+impl Handler<GetInfo> for Pmtiles {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        self.get_info()
+    }
+}
+
+impl Handler<PutTile> for Pmtiles {
+    type Result = PutTileResult;
+
+    fn handle(&mut self, msg: PutTile, _: &mut Context<Self>) -> Self::Result {
+        self.put_tile(msg.z, msg.x, msg.y, msg.data)
+    }
+}
+
+impl Handler<PutInfo> for Pmtiles {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        self.put_info(&msg.info)
+    }
+}
+
+impl Handler<StopWriting> for Pmtiles {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        self.stop_writing()
+    }
+}
+
+/// Register `pmtiles://` source and sink
+pub fn register(registry: &mut Registry) {
+    registry.register_source::<Pmtiles>("pmtiles");
+    registry.register_sink::<Pmtiles>("pmtiles");
+}
+
+#[test]
+fn test_missing_archive() {
+    let source = Pmtiles::load("pmtiles:///tmp/legeo_missing.pmtiles").unwrap();
+    match source.get_tile(0, 0, 0) {
+        Err(legeo::Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+        _ => panic!("NotFound expected"),
+    }
+}
+
+#[test]
+fn test_concurrent_writers() {
+    let _ = fs::remove_file("/tmp/legeo_concurrent.pmtiles.tmp");
+    let sink = Pmtiles::load("pmtiles:///tmp/legeo_concurrent.pmtiles").unwrap();
+    sink.put_tile(0, 0, 0, b"0/0/0".to_vec()).unwrap();
+    let other = Pmtiles::load("pmtiles:///tmp/legeo_concurrent.pmtiles").unwrap();
+    match other.put_tile(1, 0, 0, b"1/0/0".to_vec()) {
+        Err(legeo::Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::AlreadyExists),
+        _ => panic!("AlreadyExists expected"),
+    }
+    sink.stop_writing().unwrap();
+    let source = Pmtiles::load("pmtiles:///tmp/legeo_concurrent.pmtiles").unwrap();
+    assert_eq!(
+        source.get_tile(0, 0, 0).unwrap().map(|t| t.data),
+        Some(b"0/0/0".to_vec())
+    );
+}
+
+#[test]
+fn test_write_tiles() {
+    let _ = fs::remove_file("/tmp/legeo_write.pmtiles");
+    let sink = Pmtiles::load("pmtiles:///tmp/legeo_write.pmtiles").unwrap();
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    sink.put_tile(0, 0, 0, b"\x89PNG\r\n\x1a\nroot".to_vec())
+        .unwrap();
+    for x in 0..4 {
+        for y in 0..4 {
+            sink.put_tile(2, x, y, png.clone()).unwrap();
+        }
+    }
+    sink.put_tile(2, 3, 3, b"\x89PNG\r\n\x1a\n3/3".to_vec())
+        .unwrap();
+    sink.put_info(&TileInfo {
+        name: Some("legeo".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    sink.stop_writing().unwrap();
+    assert!(!Path::new("/tmp/legeo_write.pmtiles.tmp").exists());
+
+    let source = Pmtiles::load("pmtiles:///tmp/legeo_write.pmtiles").unwrap();
+    {
+        let reader = source.reader().unwrap();
+        assert_eq!(reader.header.addressed_tiles, 17);
+        assert_eq!(reader.header.tile_contents, 3);
+        assert!(reader.header.tile_entries < 17);
+        assert_eq!(reader.header.tile_type, directory::tile_type::PNG);
+        assert_eq!(reader.header.min_zoom, 0);
+        assert_eq!(reader.header.max_zoom, 2);
+    }
+    let tile = source.get_tile(2, 1, 2).unwrap().unwrap();
+    assert_eq!(tile.data, png);
+    assert_eq!(tile.content_type, "image/png");
+    assert!(tile.etag.is_some());
+    assert_eq!(
+        source.get_tile(2, 3, 3).unwrap().unwrap().data,
+        b"\x89PNG\r\n\x1a\n3/3".to_vec()
+    );
+    assert_eq!(source.get_tile(1, 0, 0).unwrap(), None);
+    assert_eq!(source.get_tile(3, 0, 0).unwrap(), None);
+
+    let info = source.get_info().unwrap();
+    assert_eq!(info.name, Some("legeo".to_string()));
+    assert_eq!(info.format, Some("png".to_string()));
+    assert_eq!(info.minzoom, Some(0));
+    assert_eq!(info.maxzoom, Some(2));
+}
+
+#[test]
+fn test_leaf_directories() {
+    let _ = fs::remove_file("/tmp/legeo_leaves.pmtiles");
+    let sink = Pmtiles::load("pmtiles:///tmp/legeo_leaves.pmtiles").unwrap();
+    let bounds = [5.9, 45.8, 10.5, 47.8];
+    sink.put_info(&TileInfo {
+        bounds: Some(bounds),
+        ..Default::default()
+    })
+    .unwrap();
+    // Distinct tiles of varying length prevent a compact root directory
+    for x in 0..256 {
+        for y in 0..256 {
+            let padding = " ".repeat(md5::compute(format!("{}/{}", x, y)).0[0] as usize);
+            sink.put_tile(8, x, y, format!("8/{}/{}{}", x, y, padding).into_bytes())
+                .unwrap();
+        }
+    }
+    drop(sink);
+
+    let source = Pmtiles::load("pmtiles:///tmp/legeo_leaves.pmtiles").unwrap();
+    assert!(source.reader().unwrap().header.leaf_length > 0);
+    assert_eq!(
+        source.get_tile(8, 200, 17).unwrap().unwrap().data,
+        format!(
+            "8/200/17{}",
+            " ".repeat(md5::compute("200/17").0[0] as usize)
+        )
+        .into_bytes()
+    );
+    assert_eq!(source.get_info().unwrap().bounds, Some(bounds));
+}
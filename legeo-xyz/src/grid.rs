@@ -6,6 +6,7 @@
 //!Tile grids
 
 use std::f64::consts;
+use std::fmt;
 
 /// Geographic extent
 #[derive(PartialEq, Clone, Debug)]
@@ -17,7 +18,7 @@ pub struct Extent {
 }
 
 /// Min and max grid cell numbers
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct ExtentInt {
     pub minx: u32,
     pub miny: u32,
@@ -29,23 +30,70 @@ pub struct ExtentInt {
 type CellIndex = (u32, u32);
 
 /// Grid origin
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Origin {
     TopLeft,
     BottomLeft, //TopRight, BottomRight
 }
 
 /// Grid units
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Unit {
     Meters,
     Degrees,
     Feet,
 }
 
+impl Unit {
+    /// Length of one unit in meters
+    pub fn meters_per_unit(&self) -> f64 {
+        match self {
+            Unit::Meters => 1.0,
+            Unit::Degrees => METERS_PER_DEGREE,
+            Unit::Feet => 0.3048,
+        }
+    }
+}
+
+/// Length of a degree at the equator
+const METERS_PER_DEGREE: f64 = 6378137.0 * 2.0 * consts::PI / 360.0;
+/// Standardized rendering pixel size of 0.28mm
+pub(crate) const PIXEL_SCREEN_WIDTH: f64 = 0.00028;
+
+/// Invalid grid definition
+#[derive(PartialEq, Debug)]
+pub enum GridError {
+    /// Extent with min >= max or non-finite values
+    InvalidExtent,
+    /// Tile width or height of zero
+    InvalidTileSize,
+    /// No resolutions or more than 255 levels
+    InvalidLevelCount(usize),
+    /// Resolution at level which is not positive or not smaller than the previous one
+    InvalidResolution(usize),
+    /// Invalid or unsupported TileMatrixSet document
+    InvalidTileMatrixSet(String),
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::InvalidExtent => write!(f, "Invalid grid extent"),
+            GridError::InvalidTileSize => write!(f, "Invalid tile size"),
+            GridError::InvalidLevelCount(n) => write!(f, "Invalid number of grid levels: {}", n),
+            GridError::InvalidResolution(zoom) => {
+                write!(f, "Invalid resolution at level {}", zoom)
+            }
+            GridError::InvalidTileMatrixSet(msg) => write!(f, "Invalid TileMatrixSet: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
 /// Tile grid
 // Credits: MapCache by Thomas Bonfort (http://mapserver.org/mapcache/)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Grid {
     /// The width of an individual tile, in pixels.
     width: u16,
@@ -68,7 +116,7 @@ pub struct Grid {
     /// pixel for most grids used in webmapping).
     resolutions: Vec<f64>,
     /// maxx/maxy for each resolution
-    level_max: Vec<CellIndex>,
+    pub(crate) level_max: Vec<CellIndex>,
     /// Grid origin
     pub origin: Origin,
 }
@@ -120,24 +168,24 @@ impl Grid {
             width: 256,
             height: 256,
             extent: Extent {
-                minx: -20037508.3427892480,
-                miny: -20037508.3427892480,
-                maxx: 20037508.3427892480,
-                maxy: 20037508.3427892480,
+                minx: -20037508.342789248,
+                miny: -20037508.342789248,
+                maxx: 20037508.342789248,
+                maxy: 20037508.342789248,
             },
             srid: 3857,
             units: Unit::Meters,
             // Formula: http://wiki.openstreetmap.org/wiki/Slippy_map_tilenames#Resolution_and_Scale
             resolutions: vec![
-                156543.0339280410,
+                156543.033928041,
                 78271.51696402048,
                 39135.75848201023,
                 19567.87924100512,
-                9783.939620502561,
-                4891.969810251280,
-                2445.984905125640,
-                1222.992452562820,
-                611.4962262814100,
+                9783.93962050256,
+                4891.96981025128,
+                2445.98490512564,
+                1222.99245256282,
+                611.49622628141,
                 305.7481131407048,
                 152.8740565703525,
                 76.43702828517624,
@@ -148,8 +196,8 @@ impl Grid {
                 2.388657133911758,
                 1.194328566955879,
                 0.5971642834779395,
-                0.2985821417389700,
-                0.1492910708694850,
+                0.29858214173897,
+                0.149291070869485,
                 0.0746455354347424,
                 0.0373227677173712,
             ],
@@ -160,6 +208,27 @@ impl Grid {
         grid
     }
 
+    /// Builder for a custom grid covering `extent`
+    pub fn builder(extent: Extent, srid: i32, units: Unit) -> GridBuilder {
+        GridBuilder {
+            extent,
+            srid,
+            units,
+            origin: Origin::BottomLeft,
+            width: 256,
+            height: 256,
+            resolutions: Vec::new(),
+        }
+    }
+
+    /// Tile size `(width, height)` in pixels
+    pub fn tile_size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+    /// Resolutions in units per pixel, from level 0 to max zoom
+    pub fn resolutions(&self) -> &[f64] {
+        &self.resolutions
+    }
     pub fn nlevels(&self) -> u8 {
         self.resolutions.len() as u8
     }
@@ -167,15 +236,9 @@ impl Grid {
         self.nlevels() - 1
     }
     pub fn pixel_width(&self, zoom: u8) -> f64 {
-        const METERS_PER_DEGREE: f64 = 6378137.0 * 2.0 * consts::PI / 360.0;
-        match self.units {
-            Unit::Meters => self.resolutions[zoom as usize],
-            Unit::Degrees => self.resolutions[zoom as usize] * METERS_PER_DEGREE,
-            Unit::Feet => self.resolutions[zoom as usize] * 0.3048,
-        }
+        self.resolutions[zoom as usize] * self.units.meters_per_unit()
     }
     pub fn scale_denominator(&self, zoom: u8) -> f64 {
-        const PIXEL_SCREEN_WIDTH: f64 = 0.00028;
         // https://github.com/mapnik/mapnik/wiki/ScaleAndPpi#scale-denominator
         // Mapnik calculates it's default at about 90.7 PPI, which originates from an assumed standard pixel size
         // of 0.28 millimeters as defined by the OGC (Open Geospatial Consortium) SLD (Styled Layer Descriptor) Specification.
@@ -206,14 +269,153 @@ impl Grid {
     pub fn ytile_from_xyz(&self, ytile: u32, zoom: u8) -> u32 {
         // y = maxy-ytile-1
         let maxy = self.level_max[zoom as usize].1;
-        let y = maxy.saturating_sub(ytile).saturating_sub(1);
-        y
+        maxy.saturating_sub(ytile).saturating_sub(1)
     }
     /// Extent of a given tile in XYZ adressing scheme
     pub fn tile_extent_xyz(&self, xtile: u32, ytile: u32, zoom: u8) -> Extent {
-        let y = self.ytile_from_xyz(ytile, zoom);
+        let y = self.origin_row(ytile, zoom);
         self.tile_extent(xtile, y, zoom)
     }
+    /// Tiles of `zoom` are split into 2x2 tiles of the next level
+    fn is_quadtree_level(&self, zoom: u8) -> bool {
+        match self.resolutions.get(zoom as usize + 1) {
+            Some(res) => (self.resolutions[zoom as usize] / res - 2.0).abs() < 1e-9,
+            None => false,
+        }
+    }
+    /// Row in the grid's origin addressing of XYZ row, and vice versa
+    fn origin_row(&self, ytile: u32, zoom: u8) -> u32 {
+        match self.origin {
+            Origin::TopLeft => ytile,
+            Origin::BottomLeft => self.ytile_from_xyz(ytile, zoom),
+        }
+    }
+    /// Parent `(xtile, ytile, zoom)` of XYZ tile.
+    /// `None` at level 0 or if the resolution of the parent level is not double.
+    pub fn parent(&self, xtile: u32, ytile: u32, zoom: u8) -> Option<(u32, u32, u8)> {
+        if zoom == 0 || !self.is_quadtree_level(zoom - 1) {
+            return None;
+        }
+        let row = self.origin_row(ytile, zoom) / 2;
+        Some((xtile / 2, self.origin_row(row, zoom - 1), zoom - 1))
+    }
+    /// Children `(xtile, ytile, zoom)` of XYZ tile within the grid.
+    /// Empty if the resolution of the next level is not half.
+    pub fn children(&self, xtile: u32, ytile: u32, zoom: u8) -> Vec<(u32, u32, u8)> {
+        if !self.is_quadtree_level(zoom) {
+            return Vec::new();
+        }
+        let (maxx, maxy) = self.level_max[zoom as usize + 1];
+        let row = self.origin_row(ytile, zoom);
+        let mut children = Vec::with_capacity(4);
+        for x in xtile * 2..xtile * 2 + 2 {
+            for r in row * 2..row * 2 + 2 {
+                if x < maxx && r < maxy {
+                    children.push((x, self.origin_row(r, zoom + 1), zoom + 1));
+                }
+            }
+        }
+        children.sort();
+        children
+    }
+    /// XYZ tile range of the descendants of XYZ tile at `level`.
+    /// `None` if a level in between is no quadtree level.
+    pub fn descendants(&self, xtile: u32, ytile: u32, zoom: u8, level: u8) -> Option<ExtentInt> {
+        if level < zoom
+            || level as usize >= self.resolutions.len()
+            || !(zoom..level).all(|z| self.is_quadtree_level(z))
+        {
+            return None;
+        }
+        let d = u32::from(level - zoom);
+        let (maxx, maxy) = self.level_max[level as usize];
+        // Range of `2^d` cells below `cell`, clipped to `max` cells
+        let span = |cell: u32, max: u32| {
+            let min = (u64::from(cell) << d).min(u64::from(max));
+            let end = ((u64::from(cell) + 1) << d).min(u64::from(max));
+            (min as u32, end as u32)
+        };
+        let (minx, endx) = span(xtile, maxx);
+        let (minrow, endrow) = span(self.origin_row(ytile, zoom), maxy);
+        let (miny, endy) = match self.origin {
+            Origin::TopLeft => (minrow, endrow),
+            Origin::BottomLeft => (maxy - endrow, maxy - minrow),
+        };
+        Some(ExtentInt {
+            minx,
+            miny,
+            maxx: endx,
+            maxy: endy,
+        })
+    }
+    /// Other children `(xtile, ytile, zoom)` of the parent of XYZ tile.
+    /// Empty at level 0 or if the grid is no quadtree at this level.
+    pub fn siblings(&self, xtile: u32, ytile: u32, zoom: u8) -> Vec<(u32, u32, u8)> {
+        match self.parent(xtile, ytile, zoom) {
+            Some((x, y, z)) => self
+                .children(x, y, z)
+                .into_iter()
+                .filter(|&tile| tile != (xtile, ytile, zoom))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+    /// Up to 8 surrounding XYZ tiles `(xtile, ytile, zoom)`.
+    /// Columns wrap around the antimeridian for grids covering all longitudes.
+    pub fn neighbours(&self, xtile: u32, ytile: u32, zoom: u8) -> Vec<(u32, u32, u8)> {
+        let (maxx, maxy) = self.level_max[zoom as usize];
+        let wrap = self.wraps_around();
+        let mut neighbours = Vec::with_capacity(8);
+        for dx in -1i64..=1 {
+            for dy in -1i64..=1 {
+                let (mut x, y) = (i64::from(xtile) + dx, i64::from(ytile) + dy);
+                if wrap {
+                    x = x.rem_euclid(i64::from(maxx));
+                }
+                if x < 0 || x >= i64::from(maxx) || y < 0 || y >= i64::from(maxy) {
+                    continue;
+                }
+                let tile = (x as u32, y as u32, zoom);
+                if tile != (xtile, ytile, zoom) && !neighbours.contains(&tile) {
+                    neighbours.push(tile);
+                }
+            }
+        }
+        neighbours.sort();
+        neighbours
+    }
+    /// Grid extent spans all longitudes
+    fn wraps_around(&self) -> bool {
+        let width = self.extent.maxx - self.extent.minx;
+        match (self.units, self.srid) {
+            (Unit::Degrees, _) => width >= 360.0 - 1e-6,
+            (Unit::Meters, 3857 | 900913 | 3395) => width >= 2.0 * 20037508.34,
+            _ => false,
+        }
+    }
+    /// XYZ tile `(xtile, ytile, zoom)` containing the point `(x, y)` in grid CRS.
+    /// `None` if the point is outside of the grid.
+    pub fn tile_at(&self, x: f64, y: f64, zoom: u8) -> Option<(u32, u32, u8)> {
+        let res = *self.resolutions.get(zoom as usize)?;
+        let unitwidth = self.width as f64 * res;
+        let unitheight = self.height as f64 * res;
+        let col = ((x - self.extent.minx) / unitwidth).floor();
+        let row = match self.origin {
+            Origin::TopLeft => ((self.extent.maxy - y) / unitheight).floor(),
+            Origin::BottomLeft => ((y - self.extent.miny) / unitheight).floor(),
+        };
+        let (maxx, maxy) = self.level_max[zoom as usize];
+        if col < 0.0 || row < 0.0 || col >= f64::from(maxx) || row >= f64::from(maxy) {
+            return None;
+        }
+        Some((col as u32, self.origin_row(row as u32, zoom), zoom))
+    }
+    /// XYZ tile `(xtile, ytile, zoom)` containing the WGS84 position.
+    /// `None` if the position is outside of the grid or can't be transformed into the grid CRS.
+    pub fn tile_at_lonlat(&self, lon: f64, lat: f64, zoom: u8) -> Option<(u32, u32, u8)> {
+        let (x, y) = self.point_from_wgs84(lon, lat)?;
+        self.tile_at(x, y, zoom)
+    }
     /// (maxx, maxy) of grid level
     pub(crate) fn level_limit(&self, zoom: u8) -> CellIndex {
         let res = self.resolutions[zoom as usize];
@@ -232,62 +434,206 @@ impl Grid {
             .map(|zoom| self.level_limit(zoom))
             .collect()
     }
-    /// Tile index limits covering extent
+    /// Extent in grid CRS of a WGS84 extent.
+    /// `None` if the grid CRS is not EPSG:4326 or a Mercator projection.
+    pub fn extent_from_wgs84(&self, extent: &Extent) -> Option<Extent> {
+        // Latitude limit of the square Mercator world
+        const MAX_LAT: f64 = 85.0511287798066;
+        let clamped = || match self.units {
+            Unit::Degrees => extent.clone(),
+            _ => Extent {
+                miny: extent.miny.clamp(-MAX_LAT, MAX_LAT),
+                maxy: extent.maxy.clamp(-MAX_LAT, MAX_LAT),
+                ..extent.clone()
+            },
+        };
+        let extent = clamped();
+        let (minx, miny) = self.point_from_wgs84(extent.minx, extent.miny)?;
+        let (maxx, maxy) = self.point_from_wgs84(extent.maxx, extent.maxy)?;
+        Some(Extent {
+            minx,
+            miny,
+            maxx,
+            maxy,
+        })
+    }
+    /// Point in grid CRS of a WGS84 position.
+    /// `None` if the grid CRS is not EPSG:4326 or a Mercator projection.
+    pub fn point_from_wgs84(&self, lon: f64, lat: f64) -> Option<(f64, f64)> {
+        match self.srid {
+            4326 => Some((lon, lat)),
+            3857 | 900913 => Some(lonlat_to_merc(lon, lat)),
+            3395 => Some(lonlat_to_world_merc(lon, lat)),
+            _ => None,
+        }
+    }
+    /// Tile index limits covering extent in XYZ adressing scheme
+    pub fn tile_limits_xyz(&self, extent: Extent, tolerance: i32) -> Vec<ExtentInt> {
+        (0..self.nlevels())
+            .map(|zoom| self.xyz_limits(self.level_tile_limits(&extent, tolerance, zoom), zoom))
+            .collect()
+    }
+    /// XYZ tile range covering extent at `zoom`, `None` if the extent is outside of the grid
+    pub fn tile_range_for_extent(&self, extent: &Extent, zoom: u8) -> Option<ExtentInt> {
+        if zoom >= self.nlevels() {
+            return None;
+        }
+        let limit = self.xyz_limits(self.level_tile_limits(extent, 0, zoom), zoom);
+        if limit.minx < limit.maxx && limit.miny < limit.maxy {
+            Some(limit)
+        } else {
+            None
+        }
+    }
+    /// Tile index limits in grid origin adressing converted to XYZ adressing
+    fn xyz_limits(&self, mut limit: ExtentInt, zoom: u8) -> ExtentInt {
+        if self.origin == Origin::BottomLeft {
+            let level_maxy = self.level_max[zoom as usize].1;
+            let (miny, maxy) = (limit.miny, limit.maxy);
+            limit.miny = level_maxy - maxy;
+            limit.maxy = level_maxy - miny;
+        }
+        limit
+    }
+    /// Tile index limits covering extent in grid origin adressing scheme
     pub fn tile_limits(&self, extent: Extent, tolerance: i32) -> Vec<ExtentInt> {
+        (0..self.nlevels())
+            .map(|zoom| self.level_tile_limits(&extent, tolerance, zoom))
+            .collect()
+    }
+    /// Tile index limits covering extent at grid level in grid origin adressing scheme
+    fn level_tile_limits(&self, extent: &Extent, tolerance: i32, zoom: u8) -> ExtentInt {
         // Based on mapcache_grid_compute_limits
         const EPSILON: f64 = 0.0000001;
-        (0..self.nlevels())
-            .map(|i| {
-                let res = self.resolutions[i as usize];
-                let unitheight = self.height as f64 * res;
-                let unitwidth = self.width as f64 * res;
-                let (level_maxx, level_maxy) = self.level_max[i as usize];
+        let res = self.resolutions[zoom as usize];
+        let unitheight = self.height as f64 * res;
+        let unitwidth = self.width as f64 * res;
+        let (level_maxx, level_maxy) = self.level_max[zoom as usize];
 
-                let (mut minx, mut maxx, mut miny, mut maxy) = match self.origin {
-                    Origin::BottomLeft => (
-                        (((extent.minx - self.extent.minx) / unitwidth + EPSILON).floor() as i32)
-                            - tolerance,
-                        (((extent.maxx - self.extent.minx) / unitwidth - EPSILON).ceil() as i32)
-                            + tolerance,
-                        (((extent.miny - self.extent.miny) / unitheight + EPSILON).floor() as i32)
-                            - tolerance,
-                        (((extent.maxy - self.extent.miny) / unitheight - EPSILON).ceil() as i32)
-                            + tolerance,
-                    ),
-                    Origin::TopLeft => (
-                        (((extent.minx - self.extent.minx) / unitwidth + EPSILON).floor() as i32)
-                            - tolerance,
-                        (((extent.maxx - self.extent.minx) / unitwidth - EPSILON).ceil() as i32)
-                            + tolerance,
-                        (((self.extent.maxy - extent.maxy) / unitheight + EPSILON).floor() as i32)
-                            - tolerance,
-                        (((self.extent.maxy - extent.miny) / unitheight - EPSILON).ceil() as i32)
-                            + tolerance,
-                    ),
-                };
+        let (mut minx, mut maxx, mut miny, mut maxy) = match self.origin {
+            Origin::BottomLeft => (
+                (((extent.minx - self.extent.minx) / unitwidth + EPSILON).floor() as i32)
+                    - tolerance,
+                (((extent.maxx - self.extent.minx) / unitwidth - EPSILON).ceil() as i32)
+                    + tolerance,
+                (((extent.miny - self.extent.miny) / unitheight + EPSILON).floor() as i32)
+                    - tolerance,
+                (((extent.maxy - self.extent.miny) / unitheight - EPSILON).ceil() as i32)
+                    + tolerance,
+            ),
+            Origin::TopLeft => (
+                (((extent.minx - self.extent.minx) / unitwidth + EPSILON).floor() as i32)
+                    - tolerance,
+                (((extent.maxx - self.extent.minx) / unitwidth - EPSILON).ceil() as i32)
+                    + tolerance,
+                (((self.extent.maxy - extent.maxy) / unitheight + EPSILON).floor() as i32)
+                    - tolerance,
+                (((self.extent.maxy - extent.miny) / unitheight - EPSILON).ceil() as i32)
+                    + tolerance,
+            ),
+        };
 
-                // to avoid requesting out-of-range tiles
-                if minx < 0 {
-                    minx = 0;
-                }
-                if maxx > level_maxx as i32 {
-                    maxx = level_maxx as i32
-                };
-                if miny < 0 {
-                    miny = 0
-                };
-                if maxy > level_maxy as i32 {
-                    maxy = level_maxy as i32
-                };
+        // to avoid requesting out-of-range tiles
+        if minx < 0 {
+            minx = 0;
+        }
+        if maxx > level_maxx as i32 {
+            maxx = level_maxx as i32
+        };
+        if miny < 0 {
+            miny = 0
+        };
+        if maxy > level_maxy as i32 {
+            maxy = level_maxy as i32
+        };
+        // empty range for extents outside of the grid
+        minx = minx.min(level_maxx as i32);
+        miny = miny.min(level_maxy as i32);
+        maxx = maxx.max(minx);
+        maxy = maxy.max(miny);
 
-                ExtentInt {
-                    minx: minx as u32,
-                    maxx: maxx as u32,
-                    miny: miny as u32,
-                    maxy: maxy as u32,
-                }
-            })
-            .collect()
+        ExtentInt {
+            minx: minx as u32,
+            maxx: maxx as u32,
+            miny: miny as u32,
+            maxy: maxy as u32,
+        }
+    }
+}
+
+/// Builder for custom grids, created with [`Grid::builder`].
+/// Defaults to 256x256 pixel tiles with bottom left origin.
+#[derive(Debug)]
+pub struct GridBuilder {
+    extent: Extent,
+    srid: i32,
+    units: Unit,
+    origin: Origin,
+    width: u16,
+    height: u16,
+    resolutions: Vec<f64>,
+}
+
+impl GridBuilder {
+    pub fn origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+    /// Tile size in pixels
+    pub fn tile_size(mut self, width: u16, height: u16) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+    /// Resolutions in units per pixel, from largest to smallest
+    pub fn resolutions(mut self, resolutions: Vec<f64>) -> Self {
+        self.resolutions = resolutions;
+        self
+    }
+    /// Resolutions given as scale denominators, from largest to smallest,
+    /// assuming a pixel size of 0.28mm
+    pub fn scale_denominators(mut self, scales: &[f64]) -> Self {
+        let meters_per_unit = self.units.meters_per_unit();
+        self.resolutions = scales
+            .iter()
+            .map(|scale| scale * PIXEL_SCREEN_WIDTH / meters_per_unit)
+            .collect();
+        self
+    }
+    /// Validate definition and build grid
+    pub fn build(self) -> Result<Grid, GridError> {
+        let e = &self.extent;
+        let finite = [e.minx, e.miny, e.maxx, e.maxy]
+            .iter()
+            .all(|v| v.is_finite());
+        if !finite || e.minx >= e.maxx || e.miny >= e.maxy {
+            return Err(GridError::InvalidExtent);
+        }
+        if self.width == 0 || self.height == 0 {
+            return Err(GridError::InvalidTileSize);
+        }
+        let nlevels = self.resolutions.len();
+        if nlevels == 0 || nlevels > u8::MAX as usize {
+            return Err(GridError::InvalidLevelCount(nlevels));
+        }
+        for (zoom, &res) in self.resolutions.iter().enumerate() {
+            let decreasing = zoom == 0 || res < self.resolutions[zoom - 1];
+            if !(res.is_finite() && res > 0.0 && decreasing) {
+                return Err(GridError::InvalidResolution(zoom));
+            }
+        }
+        let mut grid = Grid {
+            width: self.width,
+            height: self.height,
+            extent: self.extent,
+            srid: self.srid,
+            units: self.units,
+            resolutions: self.resolutions,
+            level_max: Vec::new(),
+            origin: self.origin,
+        };
+        grid.level_max = grid.level_max();
+        Ok(grid)
     }
 }
 
@@ -300,6 +646,19 @@ fn lonlat_to_merc(lon: f64, lat: f64) -> (f64, f64) {
     (x, y)
 }
 
+/// Returns the World Mercator (EPSG:3395) (x, y) in meters
+fn lonlat_to_world_merc(lon: f64, lat: f64) -> (f64, f64) {
+    // WGS84 ellipsoid eccentricity
+    const E: f64 = 0.0818191908426215;
+    let phi = lat.to_radians();
+    let esin = E * phi.sin();
+    let x = 6378137.0 * lon.to_radians();
+    let y = 6378137.0
+        * ((consts::PI * 0.25 + 0.5 * phi).tan() * ((1.0 - esin) / (1.0 + esin)).powf(E / 2.0))
+            .ln();
+    (x, y)
+}
+
 /// Projected extent
 pub fn extent_to_merc(extent: &Extent) -> Extent {
     let (minx, miny) = lonlat_to_merc(extent.minx, extent.miny);
@@ -15,6 +15,7 @@ pub struct GridIterator {
     maxz: u8,
     limits: Vec<ExtentInt>,
     finished: bool,
+    remaining: u64,
 }
 
 impl GridIterator {
@@ -22,13 +23,18 @@ impl GridIterator {
         if minz <= maxz && limits.len() > minz as usize {
             let limit = &limits[minz as usize];
             let maxz = std::cmp::min(maxz, limits.len() as u8 - 1);
+            let remaining = limits[minz as usize..=maxz as usize]
+                .iter()
+                .map(level_size)
+                .sum();
             GridIterator {
                 z: minz,
                 x: limit.minx,
                 y: limit.miny,
                 maxz,
-                limits: limits,
-                finished: false,
+                limits,
+                finished: remaining == 0,
+                remaining,
             }
         } else {
             // Return "empty" iterator for invalid parameters
@@ -39,20 +45,72 @@ impl GridIterator {
                 maxz: 0,
                 limits: Vec::new(),
                 finished: true,
+                remaining: 0,
+            }
+        }
+    }
+
+    /// Restrict iteration to part `part` (0-based) of `parts`.
+    /// The remaining cells are split into contiguous ranges of balanced size,
+    /// so that iterators of all parts cover every cell exactly once.
+    pub fn part(mut self, part: u32, parts: u32) -> GridIterator {
+        if parts <= 1 || self.finished {
+            return self;
+        }
+        let total = u128::from(self.remaining);
+        let start = (total * u128::from(part) / u128::from(parts)) as u64;
+        let end =
+            (total * u128::from(part.saturating_add(1).min(parts)) / u128::from(parts)) as u64;
+        self.advance(start);
+        self.remaining = end.saturating_sub(start);
+        if self.remaining == 0 {
+            self.finished = true;
+        }
+        self
+    }
+
+    /// Move current position `n` cells forward
+    fn advance(&mut self, n: u64) {
+        let limit = &self.limits[self.z as usize];
+        let height = u64::from(limit.maxy - limit.miny);
+        // cell offset within current level
+        let mut n = n + u64::from(self.x - limit.minx) * height + u64::from(self.y - limit.miny);
+        loop {
+            let limit = &self.limits[self.z as usize];
+            let size = level_size(limit);
+            if n < size {
+                let height = u64::from(limit.maxy - limit.miny);
+                self.x = limit.minx + (n / height) as u32;
+                self.y = limit.miny + (n % height) as u32;
+                return;
+            }
+            n -= size;
+            if self.z < self.maxz {
+                self.z += 1;
+            } else {
+                self.finished = true;
+                return;
             }
         }
     }
 }
 
+/// Number of cells in grid level
+fn level_size(limit: &ExtentInt) -> u64 {
+    u64::from(limit.maxx.saturating_sub(limit.minx))
+        * u64::from(limit.maxy.saturating_sub(limit.miny))
+}
+
 impl Iterator for GridIterator {
     /// Current cell index `(z, y, x)`
     type Item = (u8, u32, u32);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.finished {
+        if self.finished || self.remaining == 0 {
             return None;
         }
         let current = (self.z, self.x, self.y);
+        self.remaining = self.remaining.saturating_sub(1);
         let limit = &self.limits[self.z as usize];
         if self.y < limit.maxy - 1 {
             self.y += 1;
@@ -69,14 +127,22 @@ impl Iterator for GridIterator {
         }
         Some(current)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
 }
 
+impl ExactSizeIterator for GridIterator {}
+
 #[test]
 fn test_mercator_iter() {
     use crate::grid::Grid;
     let grid = Grid::web_mercator();
     let tile_limits = grid.tile_limits(grid.extent.clone(), 0);
     let griditer = GridIterator::new(0, 2, tile_limits);
+    assert_eq!(griditer.len(), 21);
     let cells = griditer.collect::<Vec<_>>();
     assert_eq!(
         cells,
@@ -195,3 +261,48 @@ fn test_bad_params() {
     let cells = griditer.collect::<Vec<_>>();
     assert_eq!(cells, vec![]);
 }
+
+#[test]
+fn test_parts() {
+    use crate::grid::Grid;
+    let grid = Grid::web_mercator();
+    let tile_limits = grid.tile_limits(grid.extent.clone(), 0);
+    let all = GridIterator::new(0, 4, tile_limits.clone()).collect::<Vec<_>>();
+    assert_eq!(all.len(), 341);
+
+    let mut cells = Vec::new();
+    for part in 0..4 {
+        let griditer = GridIterator::new(0, 4, tile_limits.clone()).part(part, 4);
+        let len = griditer.len();
+        assert!(len == 85 || len == 86);
+        let part_cells = griditer.collect::<Vec<_>>();
+        assert_eq!(part_cells.len(), len);
+        cells.extend(part_cells);
+    }
+    assert_eq!(cells, all);
+
+    let griditer = GridIterator::new(0, 2, tile_limits.clone()).part(1, 3);
+    let cells = griditer.collect::<Vec<_>>();
+    assert_eq!(
+        cells,
+        vec![
+            (2, 0, 2),
+            (2, 0, 3),
+            (2, 1, 0),
+            (2, 1, 1),
+            (2, 1, 2),
+            (2, 1, 3),
+            (2, 2, 0)
+        ]
+    );
+
+    // more parts than cells
+    let griditer = GridIterator::new(0, 0, tile_limits.clone()).part(1, 2);
+    assert_eq!(griditer.collect::<Vec<_>>(), vec![(0, 0, 0)]);
+    let griditer = GridIterator::new(0, 0, tile_limits.clone()).part(0, 2);
+    assert_eq!(griditer.collect::<Vec<_>>(), vec![]);
+
+    // invalid part
+    let griditer = GridIterator::new(0, 2, tile_limits).part(3, 3);
+    assert_eq!(griditer.collect::<Vec<_>>(), vec![]);
+}
@@ -3,12 +3,10 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use crate::grid::{extent_to_merc, Extent, ExtentInt, Grid};
+use crate::grid::{extent_to_merc, Extent, ExtentInt, Grid, GridError, Origin, Unit};
 
 #[test]
 fn test_bbox() {
-    use std::u32;
-
     let grid = Grid::web_mercator();
 
     let extent000 = grid.tile_extent(0, 0, 0);
@@ -167,6 +165,394 @@ fn test_wgs84_grid() {
     assert_eq!(grid.scale_denominator(10), 272989.38673277234);
 }
 
+/// Swiss LV95 grid (EPSG:2056) of the swisstopo WMTS
+fn lv95() -> Grid {
+    Grid::builder(
+        Extent {
+            minx: 2420000.0,
+            miny: 1030000.0,
+            maxx: 2900000.0,
+            maxy: 1350000.0,
+        },
+        2056,
+        Unit::Meters,
+    )
+    .origin(Origin::TopLeft)
+    .resolutions(vec![
+        4000.0, 3750.0, 3500.0, 3250.0, 3000.0, 2750.0, 2500.0, 2250.0, 2000.0, 1750.0, 1500.0,
+        1250.0, 1000.0, 750.0, 650.0, 500.0, 250.0, 100.0, 50.0, 20.0, 10.0, 5.0, 2.5, 2.0, 1.5,
+        1.0, 0.5, 0.25, 0.1,
+    ])
+    .build()
+    .unwrap()
+}
+
+#[test]
+fn test_custom_grid() {
+    let grid = lv95();
+    assert_eq!(grid.nlevels(), 29);
+    assert_eq!(grid.tile_size(), (256, 256));
+    assert_eq!(grid.level_limit(0), (1, 1));
+    assert_eq!(grid.level_limit(17), (19, 13));
+    assert_eq!(
+        grid.tile_extent_xyz(0, 0, 17),
+        Extent {
+            minx: 2420000.0,
+            miny: 1324400.0,
+            maxx: 2445600.0,
+            maxy: 1350000.0,
+        }
+    );
+    assert_eq!(grid.tile_extent(0, 0, 17), grid.tile_extent_xyz(0, 0, 17));
+    // Resolutions 1000 -> 750 are no quadtree levels
+    assert_eq!(grid.children(0, 0, 12), vec![]);
+    assert_eq!(grid.parent(3, 2, 18), Some((1, 1, 17)));
+
+    // Web Mercator from scale denominators
+    let mercator = Grid::web_mercator();
+    let scales = (0..mercator.nlevels())
+        .map(|zoom| mercator.scale_denominator(zoom))
+        .collect::<Vec<_>>();
+    let grid = Grid::builder(mercator.extent.clone(), 3857, Unit::Meters)
+        .tile_size(256, 256)
+        .scale_denominators(&scales)
+        .build()
+        .unwrap();
+    for (res, expected) in grid.resolutions().iter().zip(mercator.resolutions()) {
+        assert!((res - expected).abs() < 1e-6);
+    }
+    assert_eq!(grid.level_limit(10), (1024, 1024));
+}
+
+#[test]
+fn test_invalid_grid() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 100.0,
+        maxy: 100.0,
+    };
+    let builder = || Grid::builder(extent.clone(), 0, Unit::Meters);
+    assert_eq!(
+        builder().resolutions(vec![]).build().unwrap_err(),
+        GridError::InvalidLevelCount(0)
+    );
+    assert_eq!(
+        builder().resolutions(vec![2.0, 2.0]).build().unwrap_err(),
+        GridError::InvalidResolution(1)
+    );
+    assert_eq!(
+        builder().resolutions(vec![-1.0]).build().unwrap_err(),
+        GridError::InvalidResolution(0)
+    );
+    assert_eq!(
+        builder()
+            .tile_size(0, 256)
+            .resolutions(vec![1.0])
+            .build()
+            .unwrap_err(),
+        GridError::InvalidTileSize
+    );
+    let flipped = Extent {
+        minx: 100.0,
+        ..extent.clone()
+    };
+    assert_eq!(
+        Grid::builder(flipped, 0, Unit::Meters)
+            .resolutions(vec![1.0])
+            .build()
+            .unwrap_err(),
+        GridError::InvalidExtent
+    );
+}
+
+#[test]
+fn test_parent_children() {
+    let grid = Grid::web_mercator();
+    assert_eq!(grid.parent(0, 0, 0), None);
+    assert_eq!(grid.parent(1073, 717, 11), Some((536, 358, 10)));
+    assert_eq!(
+        grid.children(536, 358, 10),
+        vec![
+            (1072, 716, 11),
+            (1072, 717, 11),
+            (1073, 716, 11),
+            (1073, 717, 11)
+        ]
+    );
+    assert_eq!(grid.children(0, 0, grid.maxzoom()), vec![]);
+    for (x, y, z) in grid.children(536, 358, 10) {
+        let child = grid.tile_extent_xyz(x, y, z);
+        let parent = grid.tile_extent_xyz(536, 358, 10);
+        // Rounding errors of resolutions
+        const EPSILON: f64 = 0.001;
+        assert!(child.minx > parent.minx - EPSILON && child.maxx < parent.maxx + EPSILON);
+        assert!(child.miny > parent.miny - EPSILON && child.maxy < parent.maxy + EPSILON);
+    }
+
+    // 2x1 tiles at level 0
+    let grid = Grid::wgs84();
+    assert_eq!(
+        grid.children(1, 0, 0),
+        vec![(2, 0, 1), (2, 1, 1), (3, 0, 1), (3, 1, 1)]
+    );
+    assert_eq!(grid.parent(3, 1, 1), Some((1, 0, 0)));
+    let child = grid.tile_extent_xyz(2, 0, 1);
+    assert_eq!((child.minx, child.maxy), (0.0, 90.0));
+}
+
+#[test]
+fn test_descendants() {
+    // Compare with recursive children
+    fn check(grid: &Grid, tile: (u32, u32, u8), level: u8) {
+        let mut tiles = vec![tile];
+        for _ in tile.2..level {
+            tiles = tiles
+                .iter()
+                .flat_map(|&(x, y, z)| grid.children(x, y, z))
+                .collect();
+        }
+        let range = grid.descendants(tile.0, tile.1, tile.2, level).unwrap();
+        let mut expected = Vec::new();
+        for x in range.minx..range.maxx {
+            for y in range.miny..range.maxy {
+                expected.push((x, y, level));
+            }
+        }
+        tiles.sort();
+        assert_eq!(tiles, expected);
+    }
+    let grid = Grid::web_mercator();
+    assert_eq!(
+        grid.descendants(1, 0, 1, 3),
+        Some(ExtentInt {
+            minx: 4,
+            miny: 0,
+            maxx: 8,
+            maxy: 4
+        })
+    );
+    check(&grid, (1, 0, 1), 3);
+    check(&grid, (536, 358, 10), 13);
+    check(&Grid::wgs84(), (1, 0, 0), 3);
+    // Odd number of rows with origin at the top and bottom
+    let grid = lv95();
+    check(&grid, (3, 2, 15), 16);
+    assert_eq!(grid.descendants(0, 0, 15, 17), None);
+    let mut grid = lv95();
+    grid.origin = Origin::BottomLeft;
+    for y in 0..3 {
+        check(&grid, (1, y, 15), 16);
+    }
+}
+
+#[test]
+fn test_tile_limits_xyz() {
+    let grid = Grid::web_mercator();
+    let north_east = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 180.0,
+        maxy: 85.0,
+    };
+    let extent = grid.extent_from_wgs84(&north_east).unwrap();
+    let tms = grid.tile_limits(extent.clone(), 0);
+    let xyz = grid.tile_limits_xyz(extent, 0);
+    assert_eq!(
+        (tms[1].minx, tms[1].miny, tms[1].maxx, tms[1].maxy),
+        (1, 1, 2, 2)
+    );
+    assert_eq!(
+        xyz[1],
+        ExtentInt {
+            minx: 1,
+            miny: 0,
+            maxx: 2,
+            maxy: 1,
+        }
+    );
+    assert_eq!(
+        (xyz[3].minx, xyz[3].miny, xyz[3].maxx, xyz[3].maxy),
+        (4, 0, 8, 4)
+    );
+
+    // Top left origin
+    let grid = lv95();
+    assert_eq!(
+        grid.tile_limits_xyz(grid.extent.clone(), 0),
+        grid.tile_limits(grid.extent.clone(), 0)
+    );
+
+    let grid = Grid::wgs84();
+    let extent = grid.extent_from_wgs84(&north_east).unwrap();
+    assert_eq!(extent, north_east);
+    let xyz = grid.tile_limits_xyz(extent, 0);
+    assert_eq!(
+        (xyz[0].minx, xyz[0].miny, xyz[0].maxx, xyz[0].maxy),
+        (1, 0, 2, 1)
+    );
+    assert_eq!(
+        (xyz[1].minx, xyz[1].miny, xyz[1].maxx, xyz[1].maxy),
+        (2, 0, 4, 1)
+    );
+}
+
+#[test]
+fn test_extent_from_wgs84() {
+    let extent = Extent {
+        minx: -180.0,
+        miny: -90.0,
+        maxx: 45.0,
+        maxy: 45.0,
+    };
+    let grid = Grid::builder(Grid::web_mercator().extent, 3395, Unit::Meters)
+        .resolutions(vec![156543.033928041])
+        .build()
+        .unwrap();
+    let world_merc = grid.extent_from_wgs84(&extent).unwrap();
+    assert!((world_merc.maxy - 5591295.9185533915).abs() < 0.01);
+    assert!((world_merc.minx + 20037508.342789244).abs() < 0.01);
+    // Clamped to the Mercator world extent
+    assert!(world_merc.miny.is_finite());
+    let web_merc = Grid::web_mercator().extent_from_wgs84(&extent).unwrap();
+    assert!((web_merc.miny + 20037508.342789244).abs() < 0.01);
+    assert!(lv95().extent_from_wgs84(&extent).is_none());
+}
+
+#[test]
+fn test_tile_at() {
+    let grid = Grid::web_mercator();
+    // Mercantile: tile(20.6852, 40.1222, 9)
+    assert_eq!(
+        grid.tile_at_lonlat(20.6852, 40.1222, 9),
+        Some((285, 193, 9))
+    );
+    assert_eq!(grid.tile_at_lonlat(-180.0, 85.0, 0), Some((0, 0, 0)));
+    assert_eq!(grid.tile_at_lonlat(0.0, 89.0, 1), None);
+    let extent = grid.tile_extent_xyz(1073, 717, 11);
+    let (x, y) = (
+        (extent.minx + extent.maxx) / 2.0,
+        (extent.miny + extent.maxy) / 2.0,
+    );
+    assert_eq!(grid.tile_at(x, y, 11), Some((1073, 717, 11)));
+    assert_eq!(grid.tile_at(x, y, grid.nlevels()), None);
+
+    // 2x1 tiles at level 0
+    let grid = Grid::wgs84();
+    assert_eq!(grid.tile_at_lonlat(-170.0, 80.0, 0), Some((0, 0, 0)));
+    assert_eq!(grid.tile_at_lonlat(10.0, -80.0, 0), Some((1, 0, 0)));
+    assert_eq!(grid.tile_at_lonlat(10.0, -80.0, 1), Some((2, 1, 1)));
+    assert_eq!(grid.tile_at_lonlat(190.0, 0.0, 1), None);
+
+    // Top left origin with 19x13 tiles at level 17
+    let grid = lv95();
+    assert_eq!(grid.tile_at(2600000.0, 1200000.0, 17), Some((7, 5, 17)));
+    assert_eq!(grid.tile_at(2899999.0, 1030001.0, 17), Some((18, 12, 17)));
+    assert_eq!(grid.tile_at(2400000.0, 1200000.0, 17), None);
+    assert_eq!(grid.tile_at_lonlat(8.54, 47.37, 17), None);
+}
+
+#[test]
+fn test_siblings_neighbours() {
+    let grid = Grid::web_mercator();
+    assert_eq!(
+        grid.siblings(1073, 717, 11),
+        vec![(1072, 716, 11), (1072, 717, 11), (1073, 716, 11)]
+    );
+    assert_eq!(grid.siblings(0, 0, 0), vec![]);
+
+    // Wrap around the antimeridian
+    assert_eq!(
+        grid.neighbours(0, 5, 4),
+        vec![
+            (0, 4, 4),
+            (0, 6, 4),
+            (1, 4, 4),
+            (1, 5, 4),
+            (1, 6, 4),
+            (15, 4, 4),
+            (15, 5, 4),
+            (15, 6, 4)
+        ]
+    );
+    assert_eq!(
+        grid.neighbours(0, 0, 1),
+        vec![(0, 1, 1), (1, 0, 1), (1, 1, 1)]
+    );
+    assert_eq!(grid.neighbours(0, 0, 0), vec![]);
+    let grid = Grid::wgs84();
+    assert_eq!(grid.neighbours(0, 0, 0), vec![(1, 0, 0)]);
+    assert_eq!(
+        grid.neighbours(3, 0, 1),
+        vec![(0, 0, 1), (0, 1, 1), (2, 0, 1), (2, 1, 1), (3, 1, 1)]
+    );
+
+    // No wrap around and non-square level limits
+    let grid = lv95();
+    assert_eq!(
+        grid.neighbours(0, 0, 17),
+        vec![(0, 1, 17), (1, 0, 17), (1, 1, 17)]
+    );
+    assert_eq!(
+        grid.neighbours(18, 12, 17),
+        vec![(17, 11, 17), (17, 12, 17), (18, 11, 17)]
+    );
+    assert_eq!(grid.siblings(7, 5, 17), vec![]);
+}
+
+#[test]
+fn test_tile_range_for_extent() {
+    let grid = Grid::web_mercator();
+    let north_east = grid
+        .extent_from_wgs84(&Extent {
+            minx: 0.0,
+            miny: 0.0,
+            maxx: 180.0,
+            maxy: 85.0,
+        })
+        .unwrap();
+    assert_eq!(
+        grid.tile_range_for_extent(&north_east, 2),
+        Some(ExtentInt {
+            minx: 2,
+            miny: 0,
+            maxx: 4,
+            maxy: 2,
+        })
+    );
+    assert_eq!(
+        grid.tile_range_for_extent(&north_east, grid.nlevels()),
+        None
+    );
+
+    let grid = lv95();
+    let extent = grid.tile_extent_xyz(7, 5, 17);
+    let inner = Extent {
+        minx: extent.minx + 1.0,
+        miny: extent.miny + 1.0,
+        maxx: extent.maxx - 1.0,
+        maxy: extent.maxy - 1.0,
+    };
+    assert_eq!(
+        grid.tile_range_for_extent(&inner, 17),
+        Some(ExtentInt {
+            minx: 7,
+            miny: 5,
+            maxx: 8,
+            maxy: 6,
+        })
+    );
+    let outside = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 1000.0,
+        maxy: 1000.0,
+    };
+    assert_eq!(grid.tile_range_for_extent(&outside, 17), None);
+    let limits = grid.tile_limits(outside, 0);
+    assert_eq!(limits[17].minx, limits[17].maxx);
+}
+
 #[test]
 fn test_projected_extent() {
     let extent_wgs84 = Extent {
@@ -310,5 +696,4 @@ mod web_mercator {
             }
         );
     }
-
 }
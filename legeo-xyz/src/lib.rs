@@ -2,3 +2,5 @@ pub mod grid;
 pub mod grid_iterator;
 #[cfg(test)]
 mod grid_test;
+pub mod tile;
+pub mod tms;
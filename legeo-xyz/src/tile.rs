@@ -0,0 +1,251 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile coordinates of quadtree grids and their encodings
+
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Tile `(z, x, y)` in XYZ addressing of a quadtree grid with 2^z x 2^z tiles
+/// at level `z` and the origin at the top left
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TileCoord {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Bits per coordinate in packed tile ids
+const PACKED_BITS: u32 = 29;
+
+impl TileCoord {
+    /// Max zoom level with 32-bit columns and rows
+    pub const MAX_ZOOM: u8 = 31;
+    /// Max zoom level of packed tile ids
+    pub const MAX_PACKED_ZOOM: u8 = PACKED_BITS as u8;
+
+    /// Tile from XYZ coordinates, `None` if outside of the grid
+    pub fn new(z: u8, x: u32, y: u32) -> Option<TileCoord> {
+        if z > Self::MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+            return None;
+        }
+        Some(TileCoord { z, x, y })
+    }
+
+    /// Tile from TMS coordinates with the origin at the bottom left
+    pub fn from_tms(z: u8, x: u32, y: u32) -> Option<TileCoord> {
+        let tile = TileCoord::new(z, x, y)?;
+        Some(TileCoord {
+            y: tile.tms_y(),
+            ..tile
+        })
+    }
+
+    /// TMS row with the origin at the bottom left
+    pub fn tms_y(&self) -> u32 {
+        (1 << self.z) - 1 - self.y
+    }
+
+    /// Tile of Bing Maps quadkey, e.g. `"0231"`. The empty quadkey is tile `0/0/0`.
+    pub fn from_quadkey(quadkey: &str) -> Option<TileCoord> {
+        if quadkey.len() > Self::MAX_ZOOM as usize {
+            return None;
+        }
+        let (mut x, mut y) = (0, 0);
+        for digit in quadkey.chars() {
+            let digit = digit.to_digit(4)?;
+            x = (x << 1) | (digit & 1);
+            y = (y << 1) | (digit >> 1);
+        }
+        Some(TileCoord {
+            z: quadkey.len() as u8,
+            x,
+            y,
+        })
+    }
+
+    /// Bing Maps quadkey with one digit per zoom level
+    pub fn quadkey(&self) -> String {
+        (1..=self.z)
+            .rev()
+            .map(|level| {
+                let mask = 1 << (level - 1);
+                let digit = u32::from(self.x & mask != 0) + 2 * u32::from(self.y & mask != 0);
+                char::from_digit(digit, 4).unwrap_or('0')
+            })
+            .collect()
+    }
+
+    /// Packed 64-bit id with zoom level, column and row in separate bit fields,
+    /// `None` above `MAX_PACKED_ZOOM`
+    pub fn id(&self) -> Option<u64> {
+        if self.z > Self::MAX_PACKED_ZOOM {
+            return None;
+        }
+        Some(
+            (u64::from(self.z) << (2 * PACKED_BITS))
+                | (u64::from(self.x) << PACKED_BITS)
+                | u64::from(self.y),
+        )
+    }
+
+    /// Tile of packed 64-bit id
+    pub fn from_id(id: u64) -> Option<TileCoord> {
+        let mask = (1 << PACKED_BITS) - 1;
+        let z = u8::try_from(id >> (2 * PACKED_BITS)).ok()?;
+        if z > Self::MAX_PACKED_ZOOM {
+            return None;
+        }
+        TileCoord::new(z, ((id >> PACKED_BITS) & mask) as u32, (id & mask) as u32)
+    }
+
+    /// PMTiles tile id: Position on the Hilbert curve of its zoom level,
+    /// preceded by the tiles of all lower zoom levels.
+    pub fn hilbert_id(&self) -> u64 {
+        let base = ((1u64 << (2 * u64::from(self.z))) - 1) / 3;
+        let (mut x, mut y) = (u64::from(self.x), u64::from(self.y));
+        let mut d = 0;
+        let n = 1u64 << self.z;
+        let mut s = n / 2;
+        while s > 0 {
+            let rx = u64::from(x & s > 0);
+            let ry = u64::from(y & s > 0);
+            d += s * s * ((3 * rx) ^ ry);
+            rotate(n, &mut x, &mut y, rx, ry);
+            s /= 2;
+        }
+        base + d
+    }
+
+    /// Tile of PMTiles tile id
+    pub fn from_hilbert_id(id: u64) -> Option<TileCoord> {
+        let mut base = 0;
+        let mut z = 0;
+        loop {
+            if z > Self::MAX_ZOOM {
+                return None;
+            }
+            let count = 1u64 << (2 * u32::from(z));
+            if id < base + count {
+                break;
+            }
+            base += count;
+            z += 1;
+        }
+        let n = 1u64 << z;
+        let mut d = id - base;
+        let (mut x, mut y) = (0, 0);
+        let mut s = 1;
+        while s < n {
+            let rx = 1 & (d / 2);
+            let ry = 1 & (d ^ rx);
+            rotate(s, &mut x, &mut y, rx, ry);
+            x += s * rx;
+            y += s * ry;
+            d /= 4;
+            s *= 2;
+        }
+        Some(TileCoord {
+            z,
+            x: x as u32,
+            y: y as u32,
+        })
+    }
+}
+
+impl fmt::Display for TileCoord {
+    /// `z/x/y`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.z, self.x, self.y)
+    }
+}
+
+fn rotate(n: u64, x: &mut u64, y: &mut u64, rx: u64, ry: u64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = n - 1 - *x;
+            *y = n - 1 - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+#[cfg(test)]
+fn tile(z: u8, x: u32, y: u32) -> TileCoord {
+    TileCoord::new(z, x, y).unwrap()
+}
+
+#[test]
+fn test_tms() {
+    assert_eq!(tile(0, 0, 0).tms_y(), 0);
+    assert_eq!(tile(3, 1, 2).tms_y(), 5);
+    assert_eq!(TileCoord::from_tms(3, 1, 5), Some(tile(3, 1, 2)));
+    assert_eq!(tile(11, 1073, 717).tms_y(), 1330);
+    assert_eq!(TileCoord::new(1, 2, 0), None);
+    assert_eq!(TileCoord::from_tms(1, 0, 2), None);
+    assert_eq!(tile(31, 0, 0).tms_y(), (1 << 31) - 1);
+    assert_eq!(TileCoord::new(32, 0, 0), None);
+    assert_eq!(tile(3, 1, 2).to_string(), "3/1/2");
+}
+
+#[test]
+fn test_quadkey() {
+    // https://learn.microsoft.com/en-us/bingmaps/articles/bing-maps-tile-system
+    assert_eq!(tile(3, 3, 5).quadkey(), "213");
+    assert_eq!(TileCoord::from_quadkey("213"), Some(tile(3, 3, 5)));
+    assert_eq!(tile(0, 0, 0).quadkey(), "");
+    assert_eq!(TileCoord::from_quadkey(""), Some(tile(0, 0, 0)));
+    assert_eq!(tile(1, 1, 1).quadkey(), "3");
+    assert_eq!(TileCoord::from_quadkey("0231"), Some(tile(4, 3, 6)));
+    assert_eq!(TileCoord::from_quadkey("0241"), None);
+    for t in [
+        tile(29, 281_474_976, 123_456_789),
+        tile(31, (1 << 31) - 1, 5),
+    ] {
+        assert_eq!(TileCoord::from_quadkey(&t.quadkey()), Some(t));
+    }
+    assert_eq!(TileCoord::from_quadkey(&"0".repeat(32)), None);
+}
+
+#[test]
+fn test_packed_id() {
+    assert_eq!(tile(0, 0, 0).id(), Some(0));
+    assert_eq!(tile(1, 1, 0).id(), Some((1 << 58) | (1 << 29)));
+    for t in [
+        tile(14, 8580, 5738),
+        tile(29, (1 << 29) - 1, 0),
+        tile(5, 0, 31),
+    ] {
+        assert_eq!(t.id().and_then(TileCoord::from_id), Some(t));
+    }
+    assert_eq!(tile(30, 0, 0).id(), None);
+    // Column outside of level 1
+    assert_eq!(TileCoord::from_id((1 << 58) | (2 << 29)), None);
+    assert_eq!(TileCoord::from_id(30 << 58), None);
+}
+
+#[test]
+fn test_hilbert_id() {
+    assert_eq!(tile(0, 0, 0).hilbert_id(), 0);
+    assert_eq!(tile(1, 0, 0).hilbert_id(), 1);
+    assert_eq!(tile(1, 0, 1).hilbert_id(), 2);
+    assert_eq!(tile(1, 1, 1).hilbert_id(), 3);
+    assert_eq!(tile(1, 1, 0).hilbert_id(), 4);
+    assert_eq!(tile(2, 0, 0).hilbert_id(), 5);
+    assert_eq!(tile(3, 7, 0).hilbert_id(), 84);
+    for id in 0..1365 {
+        let tile = TileCoord::from_hilbert_id(id).unwrap();
+        assert_eq!(tile.hilbert_id(), id);
+    }
+    assert_eq!(
+        TileCoord::from_hilbert_id(tile(20, 549_000, 366_000).hilbert_id()),
+        Some(tile(20, 549_000, 366_000))
+    );
+    let max = tile(31, (1 << 31) - 1, 0);
+    assert_eq!(TileCoord::from_hilbert_id(max.hilbert_id()), Some(max));
+    let last = tile(31, (1 << 31) - 1, (1 << 31) - 1);
+    assert_eq!(TileCoord::from_hilbert_id(last.hilbert_id()), Some(last));
+    assert_eq!(TileCoord::from_hilbert_id(u64::MAX), None);
+}
@@ -0,0 +1,404 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! OGC Two Dimensional Tile Matrix Set documents
+//!
+//! JSON encoding as specified in <https://docs.ogc.org/is/17-083r4/17-083r4.html>
+
+use crate::grid::{Extent, Grid, GridError, Origin, Unit, PIXEL_SCREEN_WIDTH};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Names of the built-in registered tile matrix sets
+pub const BUILTIN: &[&str] = &[
+    "WebMercatorQuad",
+    "WorldCRS84Quad",
+    "WorldMercatorWGS84Quad",
+];
+
+const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+
+/// Tile matrix set with one tile matrix per zoom level
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TileMatrixSet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// CRS URI, e.g. `http://www.opengis.net/def/crs/EPSG/0/3857`
+    #[serde(deserialize_with = "deserialize_crs")]
+    pub crs: String,
+    /// Axis names in the order of `pointOfOrigin` coordinates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ordered_axes: Option<Vec<String>>,
+    pub tile_matrices: Vec<TileMatrix>,
+}
+
+/// Grid of tiles at one zoom level
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TileMatrix {
+    pub id: String,
+    pub scale_denominator: f64,
+    /// Resolution in CRS units per pixel
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_size: Option<f64>,
+    #[serde(default)]
+    pub corner_of_origin: CornerOfOrigin,
+    pub point_of_origin: [f64; 2],
+    pub tile_width: u16,
+    pub tile_height: u16,
+    pub matrix_width: u32,
+    pub matrix_height: u32,
+    /// Coalesced rows, not supported for grids
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable_matrix_widths: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum CornerOfOrigin {
+    #[default]
+    TopLeft,
+    BottomLeft,
+}
+
+impl From<CornerOfOrigin> for Origin {
+    fn from(corner: CornerOfOrigin) -> Origin {
+        match corner {
+            CornerOfOrigin::TopLeft => Origin::TopLeft,
+            CornerOfOrigin::BottomLeft => Origin::BottomLeft,
+        }
+    }
+}
+
+impl From<Origin> for CornerOfOrigin {
+    fn from(origin: Origin) -> CornerOfOrigin {
+        match origin {
+            Origin::TopLeft => CornerOfOrigin::TopLeft,
+            Origin::BottomLeft => CornerOfOrigin::BottomLeft,
+        }
+    }
+}
+
+/// CRS given as URI string or as object with `uri` member
+fn deserialize_crs<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Crs {
+        Uri(String),
+        Object { uri: String },
+    }
+    Ok(match Crs::deserialize(deserializer)? {
+        Crs::Uri(uri) | Crs::Object { uri } => uri,
+    })
+}
+
+/// SRID and units of CRS URI or URN, e.g. `http://www.opengis.net/def/crs/EPSG/0/2056`
+fn crs_srid(crs: &str) -> Option<(i32, Unit)> {
+    if crs.ends_with("CRS84") {
+        return Some((4326, Unit::Degrees));
+    }
+    if !crs.contains("EPSG") {
+        return None;
+    }
+    let srid = crs.rsplit(['/', ':']).next()?.parse().ok()?;
+    let units = if srid == 4326 {
+        Unit::Degrees
+    } else {
+        Unit::Meters
+    };
+    Some((srid, units))
+}
+
+impl TileMatrixSet {
+    /// Parse TileMatrixSet JSON
+    pub fn from_json(json: &str) -> Result<TileMatrixSet, GridError> {
+        serde_json::from_str(json).map_err(|e| GridError::InvalidTileMatrixSet(e.to_string()))
+    }
+
+    /// Serialize as TileMatrixSet JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Registered tile matrix set by id, see [`BUILTIN`]
+    pub fn builtin(id: &str) -> Option<TileMatrixSet> {
+        const MERCATOR_EXTENT: f64 = 20037508.3427892;
+        const MERCATOR_RESOLUTION: f64 = 156543.033928041;
+        let mercator = Extent {
+            minx: -MERCATOR_EXTENT,
+            miny: -MERCATOR_EXTENT,
+            maxx: MERCATOR_EXTENT,
+            maxy: MERCATOR_EXTENT,
+        };
+        let (title, grid) = match id {
+            "WebMercatorQuad" => (
+                "Google Maps Compatible for the World",
+                Grid::builder(mercator, 3857, Unit::Meters)
+                    .resolutions(quad_resolutions(MERCATOR_RESOLUTION, 25)),
+            ),
+            "WorldCRS84Quad" => (
+                "CRS84 for the World",
+                Grid::builder(
+                    Extent {
+                        minx: -180.0,
+                        miny: -90.0,
+                        maxx: 180.0,
+                        maxy: 90.0,
+                    },
+                    4326,
+                    Unit::Degrees,
+                )
+                .resolutions(quad_resolutions(0.703125, 18)),
+            ),
+            "WorldMercatorWGS84Quad" => (
+                "World Mercator WGS84 (ellipsoid)",
+                Grid::builder(mercator, 3395, Unit::Meters)
+                    .resolutions(quad_resolutions(MERCATOR_RESOLUTION, 25)),
+            ),
+            _ => return None,
+        };
+        let grid = grid.origin(Origin::TopLeft).build().ok()?;
+        Some(TileMatrixSet {
+            title: Some(title.to_string()),
+            uri: Some(format!(
+                "http://www.opengis.net/def/tilematrixset/OGC/1.0/{}",
+                id
+            )),
+            ..TileMatrixSet::from_grid(id, &grid)
+        })
+    }
+
+    /// Tile matrix set of grid with one tile matrix per grid level
+    pub fn from_grid(id: &str, grid: &Grid) -> TileMatrixSet {
+        let (crs, axes) = if grid.srid == 4326 && grid.units == Unit::Degrees {
+            (CRS84.to_string(), ["Lon", "Lat"])
+        } else {
+            (
+                format!("http://www.opengis.net/def/crs/EPSG/0/{}", grid.srid),
+                ["X", "Y"],
+            )
+        };
+        let point_of_origin = match grid.origin {
+            Origin::TopLeft => [grid.extent.minx, grid.extent.maxy],
+            Origin::BottomLeft => [grid.extent.minx, grid.extent.miny],
+        };
+        let (tile_width, tile_height) = grid.tile_size();
+        let tile_matrices = (0..grid.nlevels())
+            .map(|zoom| {
+                let (matrix_width, matrix_height) = grid.level_max[zoom as usize];
+                TileMatrix {
+                    id: zoom.to_string(),
+                    scale_denominator: grid.scale_denominator(zoom),
+                    cell_size: Some(grid.resolutions()[zoom as usize]),
+                    corner_of_origin: grid.origin.into(),
+                    point_of_origin,
+                    tile_width,
+                    tile_height,
+                    matrix_width,
+                    matrix_height,
+                    variable_matrix_widths: None,
+                }
+            })
+            .collect();
+        TileMatrixSet {
+            id: Some(id.to_string()),
+            title: None,
+            uri: None,
+            crs,
+            ordered_axes: Some(axes.iter().map(|axis| axis.to_string()).collect()),
+            tile_matrices,
+        }
+    }
+
+    /// Grid with zoom levels in tile matrix order.
+    /// All tile matrices must share tile size, corner and point of origin.
+    pub fn to_grid(&self) -> Result<Grid, GridError> {
+        let invalid = |msg: String| GridError::InvalidTileMatrixSet(msg);
+        let (srid, units) = crs_srid(&self.crs)
+            .ok_or_else(|| invalid(format!("Unsupported CRS `{}`", self.crs)))?;
+        // e.g. EPSG:4326 with latitude first
+        let swap_axes = self.ordered_axes.as_ref().is_some_and(|axes| {
+            axes.first().is_some_and(|axis| {
+                let axis = axis.to_lowercase();
+                axis.starts_with("lat") || axis == "n"
+            })
+        });
+        let first = self
+            .tile_matrices
+            .first()
+            .ok_or(GridError::InvalidLevelCount(0))?;
+        let [x, y] = first.point_of_origin;
+        let (originx, originy) = if swap_axes { (y, x) } else { (x, y) };
+        let mut resolutions = Vec::with_capacity(self.tile_matrices.len());
+        for matrix in &self.tile_matrices {
+            let same_origin = (matrix.point_of_origin[0] - first.point_of_origin[0]).abs() < 1e-6
+                && (matrix.point_of_origin[1] - first.point_of_origin[1]).abs() < 1e-6;
+            if !same_origin || matrix.corner_of_origin != first.corner_of_origin {
+                return Err(invalid(format!(
+                    "Tile matrix `{}` has another origin",
+                    matrix.id
+                )));
+            }
+            if (matrix.tile_width, matrix.tile_height) != (first.tile_width, first.tile_height) {
+                return Err(invalid(format!(
+                    "Tile matrix `{}` has another tile size",
+                    matrix.id
+                )));
+            }
+            if matrix.variable_matrix_widths.is_some() {
+                return Err(invalid(format!(
+                    "Variable matrix widths of tile matrix `{}` are not supported",
+                    matrix.id
+                )));
+            }
+            resolutions.push(matrix.cell_size.unwrap_or_else(|| {
+                matrix.scale_denominator * PIXEL_SCREEN_WIDTH / units.meters_per_unit()
+            }));
+        }
+        // Extent covered by the finest tile matrix
+        let last = &self.tile_matrices[self.tile_matrices.len() - 1];
+        let res = resolutions[resolutions.len() - 1];
+        let width = f64::from(last.matrix_width) * f64::from(last.tile_width) * res;
+        let height = f64::from(last.matrix_height) * f64::from(last.tile_height) * res;
+        let extent = match first.corner_of_origin {
+            CornerOfOrigin::TopLeft => Extent {
+                minx: originx,
+                miny: originy - height,
+                maxx: originx + width,
+                maxy: originy,
+            },
+            CornerOfOrigin::BottomLeft => Extent {
+                minx: originx,
+                miny: originy,
+                maxx: originx + width,
+                maxy: originy + height,
+            },
+        };
+        let mut grid = Grid::builder(extent, srid, units)
+            .origin(first.corner_of_origin.into())
+            .tile_size(first.tile_width, first.tile_height)
+            .resolutions(resolutions)
+            .build()?;
+        grid.level_max = self
+            .tile_matrices
+            .iter()
+            .map(|matrix| (matrix.matrix_width, matrix.matrix_height))
+            .collect();
+        Ok(grid)
+    }
+}
+
+/// Resolutions halved for each of `nlevels` levels
+fn quad_resolutions(res0: f64, nlevels: i32) -> Vec<f64> {
+    (0..nlevels).map(|zoom| res0 / 2f64.powi(zoom)).collect()
+}
+
+#[test]
+fn test_builtin() {
+    let tms = TileMatrixSet::builtin("WebMercatorQuad").unwrap();
+    assert_eq!(tms.crs, "http://www.opengis.net/def/crs/EPSG/0/3857");
+    assert_eq!(tms.tile_matrices.len(), 25);
+    let matrix = &tms.tile_matrices[0];
+    assert!((matrix.scale_denominator - 559082264.028717).abs() < 1e-6);
+    assert_eq!(matrix.corner_of_origin, CornerOfOrigin::TopLeft);
+    assert_eq!(
+        matrix.point_of_origin,
+        [-20037508.3427892, 20037508.3427892]
+    );
+    assert_eq!((matrix.matrix_width, matrix.matrix_height), (1, 1));
+    assert_eq!(
+        (
+            tms.tile_matrices[10].matrix_width,
+            tms.tile_matrices[10].matrix_height
+        ),
+        (1024, 1024)
+    );
+
+    // Same tile extents as XYZ tiles of the Web Mercator grid
+    let grid = tms.to_grid().unwrap();
+    let extent = grid.tile_extent_xyz(486, 332, 10);
+    let expected = Grid::web_mercator().tile_extent_xyz(486, 332, 10);
+    assert!((extent.minx - expected.minx).abs() < 0.01);
+    assert!((extent.maxy - expected.maxy).abs() < 0.01);
+
+    let grid = TileMatrixSet::builtin("WorldCRS84Quad")
+        .unwrap()
+        .to_grid()
+        .unwrap();
+    assert_eq!((grid.srid, grid.units), (4326, Unit::Degrees));
+    assert_eq!(grid.level_max[0], (2, 1));
+    assert_eq!(grid.tile_extent_xyz(1, 0, 0).minx, 0.0);
+
+    for id in BUILTIN {
+        assert!(TileMatrixSet::builtin(id).unwrap().to_grid().is_ok());
+    }
+    assert!(TileMatrixSet::builtin("GoogleCRS84Quad").is_none());
+}
+
+#[test]
+fn test_json() {
+    // Excerpt of the swisstopo LV95 tile matrix set
+    let json = r#"{
+        "id": "SwissLV95",
+        "crs": {"uri": "urn:ogc:def:crs:EPSG::2056"},
+        "orderedAxes": ["E", "N"],
+        "tileMatrices": [
+            {"id": "0", "scaleDenominator": 14285714.285714287, "pointOfOrigin": [2420000.0, 1350000.0],
+             "tileWidth": 256, "tileHeight": 256, "matrixWidth": 1, "matrixHeight": 1},
+            {"id": "17", "scaleDenominator": 357142.85714285716, "cellSize": 100.0,
+             "cornerOfOrigin": "topLeft", "pointOfOrigin": [2420000.0, 1350000.0],
+             "tileWidth": 256, "tileHeight": 256, "matrixWidth": 19, "matrixHeight": 13}
+        ]
+    }"#;
+    let tms = TileMatrixSet::from_json(json).unwrap();
+    assert_eq!(tms.crs, "urn:ogc:def:crs:EPSG::2056");
+    let grid = tms.to_grid().unwrap();
+    assert_eq!((grid.srid, grid.origin), (2056, Origin::TopLeft));
+    assert!((grid.resolutions()[0] - 4000.0).abs() < 1e-6);
+    assert_eq!(grid.level_max, vec![(1, 1), (19, 13)]);
+    assert_eq!(grid.tile_extent_xyz(0, 0, 1).maxx, 2445600.0);
+
+    // Round trip
+    let exported = TileMatrixSet::from_grid("SwissLV95", &grid);
+    let parsed = TileMatrixSet::from_json(&exported.to_json()).unwrap();
+    assert_eq!(parsed, exported);
+    assert_eq!(parsed.crs, "http://www.opengis.net/def/crs/EPSG/0/2056");
+    assert_eq!(parsed.tile_matrices[1].matrix_width, 19);
+    assert_eq!(parsed.to_grid().unwrap().level_max, grid.level_max);
+
+    // Latitude first
+    let json = r#"{
+        "crs": "http://www.opengis.net/def/crs/EPSG/0/4326",
+        "orderedAxes": ["Lat", "Lon"],
+        "tileMatrices": [{"id": "0", "scaleDenominator": 279541132.0143589,
+            "pointOfOrigin": [90.0, -180.0], "tileWidth": 256, "tileHeight": 256,
+            "matrixWidth": 2, "matrixHeight": 1}]
+    }"#;
+    let grid = TileMatrixSet::from_json(json).unwrap().to_grid().unwrap();
+    assert_eq!(grid.units, Unit::Degrees);
+    assert_eq!(
+        grid.tile_extent_xyz(0, 0, 0),
+        Extent {
+            minx: -180.0,
+            miny: -90.0,
+            maxx: 0.0,
+            maxy: 90.0,
+        }
+    );
+
+    let unsupported = json.replace("EPSG/0/4326", "OGC/0/AnsiNad83");
+    assert!(TileMatrixSet::from_json(&unsupported)
+        .unwrap()
+        .to_grid()
+        .is_err());
+    assert!(TileMatrixSet::from_json("{}").is_err());
+}
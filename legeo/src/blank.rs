@@ -0,0 +1,207 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Blank tile detection
+
+use crate::tiletype::{self, TileFormat};
+use crate::transform::decompress;
+use std::str::FromStr;
+
+/// Criterion for blank raster tiles.
+/// Empty data and vector tiles without features are always blank.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Blank {
+    /// All pixels have an alpha value of at most `max_alpha`
+    Transparent { max_alpha: u8 },
+    /// All pixels are equal, each channel differing by at most `tolerance`.
+    /// Fully transparent pixels are equal regardless of their color.
+    Uniform { tolerance: u8 },
+}
+
+impl FromStr for Blank {
+    type Err = String;
+
+    /// Parse `transparent[:max_alpha]` or `uniform[:tolerance]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, option) = match s.find(':') {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        };
+        let threshold = match option.map(|value| value.parse::<u8>()) {
+            None => 0,
+            Some(Ok(threshold)) => threshold,
+            Some(Err(_)) => return Err(format!("Invalid threshold in `{}`", s)),
+        };
+        match name {
+            "transparent" => Ok(Blank::Transparent {
+                max_alpha: threshold,
+            }),
+            "uniform" => Ok(Blank::Uniform {
+                tolerance: threshold,
+            }),
+            _ => Err(format!("Unknown blank criterion `{}`", name)),
+        }
+    }
+}
+
+impl Blank {
+    /// Check whether tile data is blank. Undecodable tiles are not blank.
+    pub fn is_blank(&self, data: &[u8]) -> bool {
+        if data.is_empty() {
+            return true;
+        }
+        match tiletype::detect(data).format {
+            TileFormat::Pbf => decompress(data)
+                .ok()
+                .and_then(|pbf| has_features(&pbf))
+                .is_some_and(|features| !features),
+            TileFormat::Png | TileFormat::Jpeg | TileFormat::Webp | TileFormat::Gif => {
+                match image::load_from_memory(data) {
+                    Ok(img) => self.is_blank_image(&img.to_rgba8()),
+                    Err(_) => false,
+                }
+            }
+            TileFormat::Unknown => false,
+        }
+    }
+
+    fn is_blank_image(&self, img: &image::RgbaImage) -> bool {
+        match *self {
+            Blank::Transparent { max_alpha } => img.pixels().all(|p| p.0[3] <= max_alpha),
+            Blank::Uniform { tolerance } => {
+                let normalized = |p: &image::Rgba<u8>| if p.0[3] == 0 { [0; 4] } else { p.0 };
+                let first = match img.pixels().next() {
+                    Some(p) => normalized(p),
+                    None => return true,
+                };
+                img.pixels().all(|p| {
+                    normalized(p)
+                        .iter()
+                        .zip(&first)
+                        .all(|(a, b)| a.max(b) - a.min(b) <= tolerance)
+                })
+            }
+        }
+    }
+}
+
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Length-delimited fields `(number, content)` of a protobuf message, `None` for invalid data
+fn bytes_fields(mut buf: &[u8]) -> Option<Vec<(u64, &[u8])>> {
+    let mut fields = Vec::new();
+    while !buf.is_empty() {
+        let key = read_varint(&mut buf)?;
+        let len = match key & 7 {
+            0 => {
+                read_varint(&mut buf)?;
+                0
+            }
+            1 => 8,
+            2 => read_varint(&mut buf)? as usize,
+            5 => 4,
+            _ => return None,
+        };
+        if len > buf.len() {
+            return None;
+        }
+        if key & 7 == 2 {
+            fields.push((key >> 3, &buf[..len]));
+        }
+        buf = &buf[len..];
+    }
+    Some(fields)
+}
+
+/// Whether a vector tile has a layer with features, `None` for invalid data
+fn has_features(pbf: &[u8]) -> Option<bool> {
+    // Tile.layers = 3, Layer.features = 2
+    for (number, layer) in bytes_fields(pbf)? {
+        if number == 3 && bytes_fields(layer)?.iter().any(|field| field.0 == 2) {
+            return Some(true);
+        }
+    }
+    Some(false)
+}
+
+#[cfg(test)]
+fn png(pixel: impl Fn(u32, u32) -> [u8; 4]) -> Vec<u8> {
+    let img = image::RgbaImage::from_fn(16, 16, |x, y| image::Rgba(pixel(x, y)));
+    let mut buf = Vec::new();
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(
+            &mut std::io::Cursor::new(&mut buf),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+    buf
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        "transparent".parse(),
+        Ok(Blank::Transparent { max_alpha: 0 })
+    );
+    assert_eq!("uniform:8".parse(), Ok(Blank::Uniform { tolerance: 8 }));
+    assert!("uniform:256".parse::<Blank>().is_err());
+    assert!("white".parse::<Blank>().is_err());
+}
+
+#[test]
+fn test_blank_raster() {
+    let transparent = Blank::Transparent { max_alpha: 0 };
+    let uniform = Blank::Uniform { tolerance: 0 };
+
+    let clear = png(|x, _| [x as u8, 0, 0, 0]);
+    assert!(transparent.is_blank(&clear));
+    assert!(uniform.is_blank(&clear));
+
+    let ocean = png(|_, _| [170, 211, 223, 255]);
+    assert!(!transparent.is_blank(&ocean));
+    assert!(uniform.is_blank(&ocean));
+
+    let noisy = png(|x, y| [170 + ((x + y) % 3) as u8, 211, 223, 255]);
+    assert!(!uniform.is_blank(&noisy));
+    assert!(Blank::Uniform { tolerance: 2 }.is_blank(&noisy));
+
+    let faint = png(|x, _| [0, 0, 0, if x == 3 { 5 } else { 0 }]);
+    assert!(!transparent.is_blank(&faint));
+    assert!(Blank::Transparent { max_alpha: 5 }.is_blank(&faint));
+
+    assert!(uniform.is_blank(b""));
+    assert!(!uniform.is_blank(b"\x89PNG\r\n\x1a\ninvalid"));
+    assert!(!uniform.is_blank(b"<html>"));
+}
+
+#[test]
+fn test_blank_vector() {
+    let uniform = Blank::Uniform { tolerance: 0 };
+    // Layer with name "water" and version 2, without features
+    let layer = b"\x0a\x05water\x78\x02";
+    let empty = [&[0x1a, layer.len() as u8][..], &layer[..]].concat();
+    assert!(uniform.is_blank(&empty));
+    let gzipped = crate::transform::compress(&empty, tiletype::Compression::Gzip).unwrap();
+    assert!(uniform.is_blank(&gzipped));
+
+    // Same layer with an empty feature
+    let layer = b"\x0a\x05water\x12\x00\x78\x02";
+    let tile = [&[0x1a, layer.len() as u8][..], &layer[..]].concat();
+    assert!(!uniform.is_blank(&tile));
+
+    // Truncated layer
+    assert!(!uniform.is_blank(b"\x1a\x10\x0a\x05water"));
+}
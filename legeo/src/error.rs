@@ -0,0 +1,93 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Error type
+
+use actix::MailboxError;
+use std::fmt;
+use std::io;
+
+/// Tileconnector and tile access errors
+#[derive(Debug)]
+pub enum Error {
+    /// URI could not be parsed
+    InvalidUri(url::ParseError),
+    /// No backend registered for URI scheme
+    UnknownScheme(String),
+    /// URI parameter with invalid or unsupported value
+    UnsupportedParameter { name: String, value: String },
+    /// File system or network error
+    Io(io::Error),
+    /// Backend specific error, e.g. database failure
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// Invalid input data, e.g. tile list or TileJSON
+    Parse(Box<dyn std::error::Error + Send + Sync>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Wrap a backend specific error
+    pub fn backend<E>(err: E) -> Error
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Error::Backend(err.into())
+    }
+
+    /// Wrap a parse error
+    pub fn parse<E>(err: E) -> Error
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Error::Parse(err.into())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidUri(e) => write!(f, "Invalid URI: {}", e),
+            Error::UnknownScheme(scheme) => {
+                write!(f, "No backend registered for scheme `{}`", scheme)
+            }
+            Error::UnsupportedParameter { name, value } => {
+                write!(f, "Unsupported value `{}` for parameter `{}`", value, name)
+            }
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Backend(e) => write!(f, "Backend error: {}", e),
+            Error::Parse(e) => write!(f, "Parse error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidUri(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Backend(e) | Error::Parse(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(err: url::ParseError) -> Error {
+        Error::InvalidUri(err)
+    }
+}
+
+impl From<MailboxError> for Error {
+    fn from(err: MailboxError) -> Error {
+        Error::backend(err.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
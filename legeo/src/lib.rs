@@ -1,5 +1,19 @@
+pub mod blank;
+mod error;
 pub mod message;
 pub mod operation;
+#[cfg(test)]
+mod operation_test;
+pub mod pool;
+pub mod raster;
+pub mod registry;
+pub mod scheme;
+pub mod tile;
 pub mod tileconnector;
+pub mod tileinfo;
 pub mod tilesink;
 pub mod tilesource;
+pub mod tiletype;
+pub mod transform;
+
+pub use crate::error::{Error, Result};
@@ -5,20 +5,46 @@
 
 //! Actor message and result types
 
-use actix::Message;
+use crate::error::Result;
+use crate::tile::Tile;
+use crate::tileinfo::TileInfo;
+use actix::prelude::*;
 
+/// Gets a tile from the data store. Parameters are in XYZ format.
+/// Returns `None` if the tile does not exist.
 pub struct GetTile {
     pub z: u8,
     pub x: u32,
     pub y: u32,
 }
 
-pub type GetTileResult = std::io::Result<Vec<u8>>;
+pub type GetTileResult = Result<Option<Tile>>;
 
 impl Message for GetTile {
     type Result = GetTileResult;
 }
 
+/// Obtains tileset metadata.
+pub struct GetInfo;
+
+pub type GetInfoResult = Result<TileInfo>;
+
+impl Message for GetInfo {
+    type Result = GetInfoResult;
+}
+
+/// Stores tileset metadata.
+#[derive(Clone)]
+pub struct PutInfo {
+    pub info: TileInfo,
+}
+
+pub type PutInfoResult = Result<()>;
+
+impl Message for PutInfo {
+    type Result = PutInfoResult;
+}
+
 /// Stores a tile into the data store. Parameters are in XYZ format.
 /// `tile` must contain the compressed image.
 pub struct PutTile {
@@ -28,12 +54,61 @@ pub struct PutTile {
     pub data: Vec<u8>,
 }
 
-pub type PutTileResult = std::io::Result<()>;
+pub type PutTileResult = Result<()>;
 
 impl Message for PutTile {
     type Result = PutTileResult;
 }
 
+/// Ends write mode. Tiles cached by the sink must be committed to the data store.
+pub struct StopWriting;
+
+pub type StopWritingResult = Result<()>;
+
+impl Message for StopWriting {
+    type Result = StopWritingResult;
+}
+
+/// Recipients of a started Tilesource actor
+#[derive(Clone)]
+pub struct SourceAddr {
+    pub get_tile: Recipient<GetTile>,
+    pub get_info: Recipient<GetInfo>,
+}
+
+impl SourceAddr {
+    pub fn new<A>(addr: Addr<A>) -> SourceAddr
+    where
+        A: Actor<Context = Context<A>> + Handler<GetTile> + Handler<GetInfo>,
+    {
+        SourceAddr {
+            get_tile: addr.clone().recipient(),
+            get_info: addr.recipient(),
+        }
+    }
+}
+
+/// Recipients of a started Tilesink actor
+#[derive(Clone)]
+pub struct SinkAddr {
+    pub put_tile: Recipient<PutTile>,
+    pub put_info: Recipient<PutInfo>,
+    pub stop_writing: Recipient<StopWriting>,
+}
+
+impl SinkAddr {
+    pub fn new<A>(addr: Addr<A>) -> SinkAddr
+    where
+        A: Actor<Context = Context<A>> + Handler<PutTile> + Handler<PutInfo> + Handler<StopWriting>,
+    {
+        SinkAddr {
+            put_tile: addr.clone().recipient(),
+            put_info: addr.clone().recipient(),
+            stop_writing: addr.recipient(),
+        }
+    }
+}
+
 /* Generic implementation seems not possible
 
 use ::actix::prelude::*;
@@ -5,12 +5,26 @@
 
 //! Tile operations
 
-use crate::message::{GetTile, PutTile};
+use crate::blank::Blank;
+use crate::error::Error;
+use crate::message::{GetInfo, GetTile, PutInfo, PutTile, SinkAddr, SourceAddr, StopWriting};
+use crate::raster::{self, RasterFormat};
+use crate::scheme::{children, descendant_count, Scheme};
+use crate::tileinfo::TileInfo;
+use crate::tiletype::TileFormat;
+use crate::transform::Pipeline;
 use ::actix::prelude::*;
-use futures::Future;
-use legeo_xyz::grid::{extent_to_merc, Extent, Grid};
+use futures::future::{self, Either, Loop};
+use futures::sync::oneshot;
+use futures::{stream, Future, Stream};
+use image::DynamicImage;
+use legeo_xyz::grid::{Extent, ExtentInt, Grid};
 use legeo_xyz::grid_iterator::GridIterator;
-use log::error;
+use log::{debug, error, warn};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::runtime::{self, TaskExecutor};
+use tokio::timer::Delay;
 
 //  From https://github.com/mapbox/tilelive/blob/master/lib/tilelive.js
 
@@ -65,39 +79,932 @@ use log::error;
 // * **--part**=[number] - the specific part to copy
 // * **--retry**=[number] - number of retry attempts
 
-pub trait TileInput {
-    fn start_actor(&self) -> Recipient<GetTile>;
+/// Tile copy options
+#[derive(Clone, Debug)]
+pub struct CopyOptions {
+    /// Tile grid of source and sink
+    pub grid: Grid,
+    /// WGS84 bounding box
+    pub bounds: Extent,
+    /// Bounding box in grid CRS, replacing `bounds`
+    pub grid_bounds: Option<Extent>,
+    /// Min zoom (inclusive)
+    pub minzoom: u8,
+    /// Max zoom (inclusive)
+    pub maxzoom: u8,
+    /// Max number of pending GetTile/PutTile requests
+    pub concurrency: usize,
+    /// Number of retry attempts for failed GetTile/PutTile requests
+    pub retry: u32,
+    /// Delay before first retry, doubled for each further attempt
+    pub retry_delay: Duration,
+    /// Total number of parts for splitting the copy into independent jobs
+    pub parts: u32,
+    /// Part to copy (0-based)
+    pub part: u32,
+    /// Tile iteration scheme
+    pub scheme: Scheme,
+    /// Transformations applied to each tile before writing
+    pub transforms: Pipeline,
+    /// Criterion for detecting blank tiles after transformation
+    pub blank: Option<Blank>,
+    /// Do not write blank tiles
+    pub skip_blank: bool,
+    /// Collect blank tiles in `CopyStats::blank_tiles`
+    pub blank_list: bool,
 }
 
-pub trait TileOutput {
-    fn start_actor(&self) -> Recipient<PutTile>;
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            grid: Grid::web_mercator(),
+            bounds: Extent {
+                minx: -180.0,
+                miny: -85.0511,
+                maxx: 180.0,
+                maxy: 85.0511,
+            },
+            grid_bounds: None,
+            minzoom: 0,
+            maxzoom: 22,
+            concurrency: 8,
+            retry: 0,
+            retry_delay: Duration::from_millis(100),
+            parts: 1,
+            part: 0,
+            scheme: Scheme::Scanline,
+            transforms: Pipeline::new(),
+            blank: None,
+            skip_blank: false,
+            blank_list: false,
+        }
+    }
+}
+
+/// Tile copy statistics
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct CopyStats {
+    /// Number of tiles to process
+    pub total: u64,
+    /// Tiles written to sink
+    pub copied: u64,
+    /// Tiles not existing in source or blank tiles not written
+    pub skipped: u64,
+    /// Tiles with read or write errors, including the descendants of failed pyramid tiles
+    pub failed: u64,
+    /// Size of copied tile data
+    pub bytes: u64,
+    /// Time since start of copy
+    pub elapsed: Duration,
+    /// Failed tiles `(z, x, y)`, without descendants of failed pyramid tiles
+    pub failed_tiles: Vec<(u8, u32, u32)>,
+    /// Blank tiles, copied or skipped
+    pub blank: u64,
+    /// Blank tiles `(z, x, y)`, collected with `CopyOptions::blank_list`
+    pub blank_tiles: Vec<(u8, u32, u32)>,
+}
+
+impl CopyStats {
+    /// Number of processed tiles
+    pub fn processed(&self) -> u64 {
+        self.copied + self.skipped + self.failed
+    }
+    /// Processed tiles per second
+    pub fn tiles_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.processed() as f64 / secs
+        } else {
+            0.0
+        }
+    }
+    /// Estimated time to completion
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.tiles_per_sec();
+        if rate > 0.0 {
+            let remaining = self.total.saturating_sub(self.processed());
+            Some(Duration::from_secs_f64(remaining as f64 / rate))
+        } else {
+            None
+        }
+    }
+}
+
+impl CopyStats {
+    /// Count processed tile
+    fn count(&mut self, tile: (u8, u32, u32), status: &TileStatus, blank_list: bool) {
+        match *status {
+            TileStatus::Copied(size) => {
+                self.copied += 1;
+                self.bytes += size as u64;
+            }
+            TileStatus::Blank(size, copied) => {
+                if copied {
+                    self.copied += 1;
+                    self.bytes += size as u64;
+                } else {
+                    self.skipped += 1;
+                }
+                self.blank += 1;
+                if blank_list {
+                    self.blank_tiles.push(tile);
+                }
+            }
+            TileStatus::Skipped => self.skipped += 1,
+            TileStatus::Failed => {
+                self.failed += 1;
+                self.failed_tiles.push(tile);
+            }
+        }
+    }
 }
 
+enum TileStatus {
+    /// Tile copied with given size
+    Copied(usize),
+    /// Blank tile with given size, copied if `true`
+    Blank(usize, bool),
+    Skipped,
+    Failed,
+}
+
+/// Processing of tile data between reading and writing
+#[derive(Clone)]
+struct Processing {
+    transforms: Pipeline,
+    blank: Option<Blank>,
+    skip_blank: bool,
+}
+
+/// Retry settings for a single request
+#[derive(Clone, Copy)]
+struct Retry {
+    attempts: u32,
+    delay: Duration,
+}
+
+/// Future completing after `duration`.
+/// The timer runs in a shared runtime, because the actix runtime is blocked during copy.
+fn delay(duration: Duration) -> impl Future<Item = (), Error = Error> {
+    static TIMER: OnceLock<Option<TaskExecutor>> = OnceLock::new();
+    let timer = TIMER.get_or_init(|| {
+        let runtime = runtime::Builder::new()
+            .core_threads(1)
+            .name_prefix("legeo-timer-")
+            .build()
+            .map_err(|e| error!("Timer runtime: {}", e))
+            .ok()?;
+        let executor = runtime.executor();
+        // Keep the runtime running for the lifetime of the process
+        std::mem::forget(runtime);
+        Some(executor)
+    });
+    let (tx, rx) = oneshot::channel();
+    let deadline = Instant::now() + duration;
+    match timer {
+        Some(timer) => timer.spawn(future::lazy(move || {
+            Delay::new(deadline).then(move |_| {
+                let _ = tx.send(());
+                Ok(())
+            })
+        })),
+        // Retry without waiting
+        None => {
+            let _ = tx.send(());
+        }
+    }
+    rx.map_err(|_| Error::backend("Retry timer stopped"))
+}
+
+/// Run `request` until it succeeds or the retry attempts are exhausted
+fn with_retry<T, F, R>(
+    retry: Retry,
+    description: String,
+    mut request: F,
+) -> impl Future<Item = Result<T, Error>, Error = Error>
+where
+    F: FnMut() -> R,
+    R: Future<Item = T, Error = Error>,
+{
+    future::loop_fn(0, move |attempt| {
+        let description = description.clone();
+        request().then(move |res| match res {
+            Ok(value) => Either::A(future::ok(Loop::Break(Ok(value)))),
+            Err(e) if attempt < retry.attempts => {
+                warn!(
+                    "{}: {} (retry {}/{})",
+                    description,
+                    e,
+                    attempt + 1,
+                    retry.attempts
+                );
+                let wait = retry.delay * 2u32.saturating_pow(attempt);
+                Either::B(delay(wait).map(move |_| Loop::Continue(attempt + 1)))
+            }
+            Err(e) => {
+                error!("{}: {}", description, e);
+                Either::A(future::ok(Loop::Break(Err(e))))
+            }
+        })
+    })
+}
+
+fn copy_tile(
+    srcaddr: Recipient<GetTile>,
+    dstaddr: Recipient<PutTile>,
+    retry: Retry,
+    processing: Processing,
+    (z, x, y): (u8, u32, u32),
+) -> impl Future<Item = ((u8, u32, u32), TileStatus), Error = Error> {
+    with_retry(retry, format!("GetTile {}/{}/{}", z, x, y), move || {
+        srcaddr
+            .send(GetTile { z, x, y })
+            .map_err(Error::from)
+            .and_then(|res| res)
+    })
+    .and_then(move |res| match res {
+        Ok(Some(tile)) => {
+            let data = match processing.transforms.transform(z, x, y, tile.data) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!("Transform {}/{}/{}: {}", z, x, y, e);
+                    return Either::B(future::ok(TileStatus::Failed));
+                }
+            };
+            let size = data.len();
+            let blank = processing.blank.is_some_and(|blank| blank.is_blank(&data));
+            if blank && processing.skip_blank {
+                debug!("Tile {}/{}/{} is blank", z, x, y);
+                return Either::B(future::ok(TileStatus::Blank(size, false)));
+            }
+            let put = with_retry(retry, format!("PutTile {}/{}/{}", z, x, y), move || {
+                dstaddr
+                    .send(PutTile {
+                        z,
+                        x,
+                        y,
+                        data: data.clone(),
+                    })
+                    .map_err(Error::from)
+                    .and_then(|res| res)
+            })
+            .map(move |res| match res {
+                Ok(()) if blank => TileStatus::Blank(size, true),
+                Ok(()) => TileStatus::Copied(size),
+                Err(_) => TileStatus::Failed,
+            });
+            Either::A(put)
+        }
+        Ok(None) => {
+            debug!("Tile {}/{}/{} does not exist", z, x, y);
+            Either::B(future::ok(TileStatus::Skipped))
+        }
+        Err(_) => Either::B(future::ok(TileStatus::Failed)),
+    })
+    .map(move |status| ((z, x, y), status))
+}
+
+/// Copy `tiles` with `concurrency` pending requests.
+/// `processed` is called for each tile before updating the progress.
+fn copy_tiles<I, P, F>(
+    srcaddr: &Recipient<GetTile>,
+    dstaddr: &Recipient<PutTile>,
+    options: &CopyOptions,
+    tiles: I,
+    stats: CopyStats,
+    progress: &mut F,
+    mut processed: P,
+) -> Result<CopyStats, Error>
+where
+    I: Iterator<Item = (u8, u32, u32)>,
+    P: FnMut(&mut CopyStats, (u8, u32, u32), &TileStatus),
+    F: FnMut(&CopyStats),
+{
+    let retry = Retry {
+        attempts: options.retry,
+        delay: options.retry_delay,
+    };
+    let start = Instant::now() - stats.elapsed;
+    let (srcaddr, dstaddr) = (srcaddr.clone(), dstaddr.clone());
+    let blank_list = options.blank_list;
+    let processing = Processing {
+        transforms: options.transforms.clone(),
+        blank: options.blank,
+        skip_blank: options.skip_blank,
+    };
+    stream::iter_ok(tiles)
+        .map(move |tile| {
+            copy_tile(
+                srcaddr.clone(),
+                dstaddr.clone(),
+                retry,
+                processing.clone(),
+                tile,
+            )
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .fold(stats, |mut stats, (tile, status)| {
+            stats.count(tile, &status, blank_list);
+            processed(&mut stats, tile, &status);
+            stats.elapsed = start.elapsed();
+            progress(&stats);
+            Ok::<_, Error>(stats)
+        })
+        .wait()
+}
+
+/// Range of part `part` of `parts` in a sequence of `len` elements
+fn part_range(len: usize, part: u32, parts: u32) -> std::ops::Range<usize> {
+    let parts = parts.max(1) as usize;
+    let part = (part as usize).min(parts);
+    len * part / parts..len * (part + 1).min(parts) / parts
+}
+
+/// Copy tileset metadata, restricted to the copied zoom levels and bounds
+/// and adapted by the tile transformations
+fn copy_info(src: &SourceAddr, dst: &SinkAddr, options: &CopyOptions) -> Result<(), Error> {
+    let mut info = src
+        .get_info
+        .send(GetInfo)
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait()?;
+    // Tile lists are not restricted by zoom levels and bounds
+    if !matches!(options.scheme, Scheme::List(_)) {
+        info.minzoom = Some(
+            info.minzoom
+                .map_or(options.minzoom, |z| z.max(options.minzoom)),
+        );
+        info.maxzoom = Some(
+            info.maxzoom
+                .map_or(options.maxzoom, |z| z.min(options.maxzoom)),
+        );
+        if options.grid_bounds.is_none() {
+            let b = &options.bounds;
+            info.bounds = Some(match info.bounds {
+                Some([w, s, e, n]) => [w.max(b.minx), s.max(b.miny), e.min(b.maxx), n.min(b.maxy)],
+                None => [b.minx, b.miny, b.maxx, b.maxy],
+            });
+        }
+    }
+    let info = options.transforms.transform_info(info);
+    dst.put_info
+        .send(PutInfo { info })
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait()
+}
+
+/// XYZ tile limits of `grid_bounds` in grid CRS or of WGS84 `bounds` in `grid`.
+/// Fails if `bounds` can't be transformed into the grid CRS.
+fn grid_limits(
+    grid: &Grid,
+    bounds: &Extent,
+    grid_bounds: Option<&Extent>,
+) -> Result<Vec<ExtentInt>, Error> {
+    let extent = match grid_bounds {
+        Some(extent) => extent.clone(),
+        None => grid
+            .extent_from_wgs84(bounds)
+            .ok_or_else(|| Error::UnsupportedParameter {
+                name: "bounds".to_string(),
+                value: format!(
+                    "{},{},{},{}",
+                    bounds.minx, bounds.miny, bounds.maxx, bounds.maxy
+                ),
+            })?,
+    };
+    Ok(grid.tile_limits_xyz(extent, 0))
+}
+
+type TileList = Vec<(u8, u32, u32)>;
+
+/// Tiles of part `options.part` of a pyramid copy: Tiles copied before the pyramid
+/// and the pyramid roots.
+///
+/// Parts are split at the first level with at least `options.parts` tiles.
+/// Its tiles are assigned to parts in contiguous ranges of balanced subtree sizes.
+/// Tiles of the levels above are copied by the first part without skipping subtrees.
+fn pyramid_part(
+    grid: &Grid,
+    tile_limits: &[ExtentInt],
+    options: &CopyOptions,
+    maxzoom: u8,
+) -> (TileList, TileList) {
+    let level = |z| GridIterator::new(z, z, tile_limits.to_vec());
+    if options.parts <= 1 {
+        return (Vec::new(), level(options.minzoom).collect());
+    }
+    let mut split = options.minzoom;
+    while split < maxzoom && (level(split).len() as u64) < u64::from(options.parts) {
+        split += 1;
+    }
+    let upper = if options.part == 0 && split > options.minzoom {
+        GridIterator::new(options.minzoom, split - 1, tile_limits.to_vec()).collect()
+    } else {
+        Vec::new()
+    };
+    let roots = level(split)
+        .map(|tile| (tile, 1 + descendant_count(grid, tile_limits, tile, maxzoom)))
+        .collect::<Vec<_>>();
+    let total = u128::from(roots.iter().map(|(_, weight)| weight).sum::<u64>());
+    let parts = u128::from(options.parts);
+    let mut start = 0;
+    let roots = roots
+        .into_iter()
+        .filter(|&(_, weight)| {
+            // Part containing the first tile of this subtree
+            let part = u128::from(start) * parts / total.max(1);
+            start += weight;
+            part == u128::from(options.part)
+        })
+        .map(|(tile, _)| tile)
+        .collect();
+    (upper, roots)
+}
+
+/// Copy tileset metadata and tiles from source to sink actor and commit them with `StopWriting`.
+/// Blocks until all tiles are copied, so the actors have to run in other arbiters.
+/// Fails before copying if bounds or scheme don't fit the grid.
 pub fn tile_copy(
-    src: impl TileInput,
-    dst: impl TileOutput,
-    bounds: Extent,
-    minzoom: u8,
+    src: SourceAddr,
+    dst: SinkAddr,
+    options: &CopyOptions,
+) -> Result<CopyStats, Error> {
+    tile_copy_with_progress(src, dst, options, |_| {})
+}
+
+/// Copy tileset metadata and tiles from source to sink actor,
+/// calling `progress` after each processed tile.
+pub fn tile_copy_with_progress<F>(
+    src: SourceAddr,
+    dst: SinkAddr,
+    options: &CopyOptions,
+    mut progress: F,
+) -> Result<CopyStats, Error>
+where
+    F: FnMut(&CopyStats),
+{
+    let tile_limits = grid_limits(&options.grid, &options.bounds, options.grid_bounds.as_ref())?;
+    // Pyramids are built from the children of copied tiles
+    let maxzoom = options
+        .maxzoom
+        .min(tile_limits.len().saturating_sub(1) as u8);
+    if options.scheme == Scheme::Pyramid
+        && options.minzoom < maxzoom
+        && options
+            .grid
+            .descendants(0, 0, options.minzoom, maxzoom)
+            .is_none()
+    {
+        return Err(Error::UnsupportedParameter {
+            name: "scheme".to_string(),
+            value: "pyramid".to_string(),
+        });
+    }
+    if let Err(e) = copy_info(&src, &dst, options) {
+        error!("Copying info: {}", e);
+    }
+    let srcaddr = src.get_tile.clone();
+    let dstaddr = dst.put_tile.clone();
+    let copied = match &options.scheme {
+        Scheme::Scanline => {
+            let griditer = GridIterator::new(options.minzoom, options.maxzoom, tile_limits)
+                .part(options.part, options.parts);
+            let stats = CopyStats {
+                total: griditer.len() as u64,
+                ..Default::default()
+            };
+            copy_tiles(
+                &srcaddr,
+                &dstaddr,
+                options,
+                griditer,
+                stats,
+                &mut progress,
+                |_, _, _| {},
+            )
+        }
+        Scheme::List(tiles) => {
+            let tiles = &tiles[part_range(tiles.len(), options.part, options.parts)];
+            let stats = CopyStats {
+                total: tiles.len() as u64,
+                ..Default::default()
+            };
+            copy_tiles(
+                &srcaddr,
+                &dstaddr,
+                options,
+                tiles.iter().cloned(),
+                stats,
+                &mut progress,
+                |_, _, _| {},
+            )
+        }
+        Scheme::Pyramid => copy_pyramid(
+            &srcaddr,
+            &dstaddr,
+            options,
+            &tile_limits,
+            maxzoom,
+            &mut progress,
+        ),
+    };
+    let res = dst
+        .stop_writing
+        .send(StopWriting)
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait();
+    if let Err(e) = res {
+        error!("StopWriting: {}", e);
+    }
+    copied
+}
+
+/// Copy parents before children, without visiting subtrees of missing, empty or failed tiles
+fn copy_pyramid<F>(
+    srcaddr: &Recipient<GetTile>,
+    dstaddr: &Recipient<PutTile>,
+    options: &CopyOptions,
+    tile_limits: &[ExtentInt],
     maxzoom: u8,
-) {
-    let srcaddr = src.start_actor();
-    let dstaddr = dst.start_actor();
-
-    let grid = Grid::web_mercator();
-    let tile_limits = grid.tile_limits(extent_to_merc(&bounds), 0);
-    let griditer = GridIterator::new(minzoom, maxzoom, tile_limits);
-    for (z, x, y) in griditer {
-        let res = srcaddr
+    progress: &mut F,
+) -> Result<CopyStats, Error>
+where
+    F: FnMut(&CopyStats),
+{
+    let grid = &options.grid;
+    let (upper, roots) = pyramid_part(grid, tile_limits, options, maxzoom);
+    let mut stats = CopyStats {
+        total: upper.len() as u64
+            + roots
+                .iter()
+                .map(|&tile| 1 + descendant_count(grid, tile_limits, tile, maxzoom))
+                .sum::<u64>(),
+        ..Default::default()
+    };
+    stats = copy_tiles(
+        srcaddr,
+        dstaddr,
+        options,
+        upper.into_iter(),
+        stats,
+        progress,
+        |_, _, _| {},
+    )?;
+    let mut level = roots;
+    while !level.is_empty() {
+        let mut parents = Vec::new();
+        stats = copy_tiles(
+            srcaddr,
+            dstaddr,
+            options,
+            level.into_iter(),
+            stats,
+            progress,
+            |stats, tile, status| match status {
+                TileStatus::Copied(size) | TileStatus::Blank(size, _) if *size > 0 => {
+                    if tile.0 < maxzoom {
+                        parents.push(tile)
+                    }
+                }
+                TileStatus::Failed => {
+                    // Subtree of failed tile is not visited
+                    stats.failed += descendant_count(grid, tile_limits, tile, maxzoom);
+                }
+                _ => {
+                    // Skip subtree of missing or empty tile
+                    stats.skipped += descendant_count(grid, tile_limits, tile, maxzoom);
+                }
+            },
+        )?;
+        level = parents
+            .iter()
+            .flat_map(|&tile| children(grid, tile_limits, tile))
+            .collect();
+    }
+    Ok(stats)
+}
+
+/// Options for generating zoom levels from existing raster tiles
+#[derive(Clone, Debug)]
+pub struct ZoomOptions {
+    /// Tile grid of source and sink
+    pub grid: Grid,
+    /// WGS84 bounding box
+    pub bounds: Extent,
+    /// Bounding box in grid CRS, replacing `bounds`
+    pub grid_bounds: Option<Extent>,
+    /// Min zoom of generated levels (inclusive)
+    pub minzoom: u8,
+    /// Max zoom of generated levels (inclusive)
+    pub maxzoom: u8,
+    /// Min zoom of source tiles. Lower levels are mosaicked from downsampled tiles.
+    pub source_minzoom: u8,
+    /// Max zoom of source tiles. Higher levels are cropped and upscaled from parent tiles.
+    pub source_maxzoom: u8,
+    /// Output format [default: format of source tiles]
+    pub format: Option<RasterFormat>,
+}
+
+impl Default for ZoomOptions {
+    fn default() -> Self {
+        let defaults = CopyOptions::default();
+        ZoomOptions {
+            grid: defaults.grid,
+            bounds: defaults.bounds,
+            grid_bounds: None,
+            minzoom: 0,
+            maxzoom: 22,
+            source_minzoom: 0,
+            source_maxzoom: 22,
+            format: None,
+        }
+    }
+}
+
+/// Generation of raster tiles from source tiles of other zoom levels
+struct Zoom<'a, F> {
+    grid: Grid,
+    limits: Vec<ExtentInt>,
+    src: Recipient<GetTile>,
+    dst: Recipient<PutTile>,
+    format: Option<RasterFormat>,
+    stats: CopyStats,
+    start: Instant,
+    progress: &'a mut F,
+}
+
+impl<F> Zoom<'_, F>
+where
+    F: FnMut(&CopyStats),
+{
+    fn in_limits(&self, (z, x, y): (u8, u32, u32)) -> bool {
+        self.limits.get(z as usize).is_some_and(|limit| {
+            x >= limit.minx && x < limit.maxx && y >= limit.miny && y < limit.maxy
+        })
+    }
+
+    fn count(&mut self, tile: (u8, u32, u32), status: TileStatus) {
+        self.stats.count(tile, &status, false);
+        self.stats.elapsed = self.start.elapsed();
+        (self.progress)(&self.stats);
+    }
+
+    /// Decoded source tile, `None` if missing or empty
+    fn get_image(
+        &self,
+        (z, x, y): (u8, u32, u32),
+    ) -> Result<Option<(DynamicImage, TileFormat)>, Error> {
+        let tile = self
+            .src
             .send(GetTile { z, x, y })
-            .and_then(|tile| {
-                dstaddr.send(PutTile {
-                    z,
-                    x,
-                    y,
-                    data: tile.unwrap(),
-                })
-            })
-            .map_err(|err| error!("{:?}", err));
-        let _ = res.wait();
+            .map_err(Error::from)
+            .and_then(|res| res)
+            .wait()?;
+        match tile {
+            Some(tile) if !tile.data.is_empty() => match raster::decode(&tile.data) {
+                Some(res) => res.map(Some),
+                None => Err(Error::backend("Not a raster tile")),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn put_image(&mut self, (z, x, y): (u8, u32, u32), img: &DynamicImage, format: TileFormat) {
+        let format = self
+            .format
+            .unwrap_or_else(|| RasterFormat::from_format(format));
+        let res = raster::encode(img, format).and_then(|data| {
+            let size = data.len();
+            self.dst
+                .send(PutTile { z, x, y, data })
+                .map_err(Error::from)
+                .and_then(|res| res)
+                .wait()
+                .map(|_| size)
+        });
+        match res {
+            Ok(size) => self.count((z, x, y), TileStatus::Copied(size)),
+            Err(e) => {
+                error!("PutTile {}/{}/{}: {}", z, x, y, e);
+                self.count((z, x, y), TileStatus::Failed)
+            }
+        }
+    }
+
+    /// Descendants of `tile` within limits at levels `minz` to `maxz`
+    fn descendants(&self, tile: (u8, u32, u32), minz: u8, maxz: u8) -> Vec<(u8, u32, u32)> {
+        let mut level = vec![tile];
+        let mut tiles = Vec::new();
+        for z in tile.0 + 1..=maxz {
+            level = level
+                .iter()
+                .flat_map(|&(z, x, y)| self.grid.children(x, y, z))
+                .map(|(x, y, z)| (z, x, y))
+                .filter(|&tile| self.in_limits(tile))
+                .collect();
+            if z >= minz {
+                tiles.extend(&level);
+            }
+        }
+        tiles
+    }
+
+    /// Part of `img` covering the descendant `tile` of `parent`, upscaled to tile size
+    fn crop(
+        &self,
+        parent: (u8, u32, u32),
+        tile: (u8, u32, u32),
+        img: &DynamicImage,
+    ) -> DynamicImage {
+        let outer = self.grid.tile_extent_xyz(parent.1, parent.2, parent.0);
+        let inner = self.grid.tile_extent_xyz(tile.1, tile.2, tile.0);
+        let scalex = f64::from(img.width()) / (outer.maxx - outer.minx);
+        let scaley = f64::from(img.height()) / (outer.maxy - outer.miny);
+        raster::upscale(
+            img,
+            ((inner.minx - outer.minx) * scalex).round() as u32,
+            ((outer.maxy - inner.maxy) * scaley).round() as u32,
+            ((inner.maxx - inner.minx) * scalex).round() as u32,
+            ((inner.maxy - inner.miny) * scaley).round() as u32,
+        )
+    }
+
+    /// Generate levels `minz` to `maxz` from source tiles at level `sourcez` below them
+    fn overzoom(&mut self, minz: u8, maxz: u8, sourcez: u8) {
+        for parent in GridIterator::new(sourcez, sourcez, self.limits.clone()) {
+            let tiles = self.descendants(parent, minz, maxz);
+            if tiles.is_empty() {
+                continue;
+            }
+            match self.get_image(parent) {
+                Ok(Some((img, format))) => {
+                    for tile in tiles {
+                        let img = self.crop(parent, tile, &img);
+                        self.put_image(tile, &img, format);
+                    }
+                }
+                Ok(None) => {
+                    debug!("Tile {}/{}/{} does not exist", parent.0, parent.1, parent.2);
+                    for tile in tiles {
+                        self.count(tile, TileStatus::Skipped);
+                    }
+                }
+                Err(e) => {
+                    error!("GetTile {}/{}/{}: {}", parent.0, parent.1, parent.2, e);
+                    for tile in tiles {
+                        self.count(tile, TileStatus::Failed);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Mosaic of `tile` built from source tiles at level `sourcez`,
+    /// written if its level is at most `maxz`
+    fn underzoom(
+        &mut self,
+        tile: (u8, u32, u32),
+        maxz: u8,
+        sourcez: u8,
+    ) -> Option<(DynamicImage, TileFormat)> {
+        let (z, x, y) = tile;
+        if z == sourcez {
+            return self.get_image(tile).unwrap_or_else(|e| {
+                error!("GetTile {}/{}/{}: {}", z, x, y, e);
+                None
+            });
+        }
+        let write = z <= maxz && self.in_limits(tile);
+        let extent = self.grid.tile_extent_xyz(x, y, z);
+        let (centerx, centery) = (
+            (extent.minx + extent.maxx) / 2.0,
+            (extent.miny + extent.maxy) / 2.0,
+        );
+        let mut children = Vec::new();
+        let mut format = None;
+        // Subtrees outside of the bounds are neither read nor written
+        for (cx, cy, cz) in self.grid.children(x, y, z) {
+            if !self.in_limits((cz, cx, cy)) {
+                continue;
+            }
+            if let Some((img, child_format)) = self.underzoom((cz, cx, cy), maxz, sourcez) {
+                let child = self.grid.tile_extent_xyz(cx, cy, cz);
+                let column = u32::from((child.minx + child.maxx) / 2.0 > centerx);
+                let row = u32::from((child.miny + child.maxy) / 2.0 < centery);
+                format.get_or_insert(child_format);
+                children.push((column, row, img));
+            }
+        }
+        let format = match format {
+            Some(format) => format,
+            None => {
+                if write {
+                    self.count(tile, TileStatus::Skipped);
+                }
+                return None;
+            }
+        };
+        let (width, height) = (children[0].2.width(), children[0].2.height());
+        let img = raster::mosaic(&children, width, height);
+        if write {
+            self.put_image(tile, &img, format);
+        }
+        Some((img, format))
+    }
+}
+
+/// Tileset metadata with zoom range extended by the generated levels
+fn zoom_info(src: &SourceAddr, dst: &SinkAddr, options: &ZoomOptions) -> Result<(), Error> {
+    let info = src
+        .get_info
+        .send(GetInfo)
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait()?;
+    let info = TileInfo {
+        minzoom: Some(options.minzoom.min(options.source_minzoom)),
+        maxzoom: Some(options.maxzoom.max(options.source_maxzoom)),
+        format: match options.format {
+            Some(format) => Some(format.format().name().to_string()),
+            None => info.format,
+        },
+        ..info
+    };
+    dst.put_info
+        .send(PutInfo { info })
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait()
+}
+
+/// Generate raster tiles of levels `minzoom` to `maxzoom` outside the source zoom range
+/// and commit them with `StopWriting`.
+/// Lower levels are mosaicked from tiles at `source_minzoom`,
+/// higher levels are upscaled from tiles at `source_maxzoom`.
+/// Tiles are read and written one at a time, without concurrent requests.
+/// Blocks until all tiles are written, so the actors have to run in other arbiters.
+pub fn tile_zoom(
+    src: SourceAddr,
+    dst: SinkAddr,
+    options: &ZoomOptions,
+) -> Result<CopyStats, Error> {
+    tile_zoom_with_progress(src, dst, options, |_| {})
+}
+
+/// Generate raster tiles of other zoom levels, calling `progress` after each processed tile.
+pub fn tile_zoom_with_progress<F>(
+    src: SourceAddr,
+    dst: SinkAddr,
+    options: &ZoomOptions,
+    mut progress: F,
+) -> Result<CopyStats, Error>
+where
+    F: FnMut(&CopyStats),
+{
+    let grid = options.grid.clone();
+    let limits = grid_limits(&grid, &options.bounds, options.grid_bounds.as_ref())?;
+    let maxzoom = options.maxzoom.min(limits.len().saturating_sub(1) as u8);
+    if let Err(e) = zoom_info(&src, &dst, options) {
+        error!("Writing info: {}", e);
+    }
+    let mut zoom = Zoom {
+        grid,
+        limits,
+        src: src.get_tile.clone(),
+        dst: dst.put_tile.clone(),
+        format: options.format,
+        stats: CopyStats::default(),
+        start: Instant::now(),
+        progress: &mut progress,
+    };
+    let lower = (options.minzoom < options.source_minzoom)
+        .then(|| (options.minzoom, maxzoom.min(options.source_minzoom - 1)))
+        .filter(|(minz, maxz)| minz <= maxz);
+    let higher = (maxzoom > options.source_maxzoom)
+        .then(|| (options.minzoom.max(options.source_maxzoom + 1), maxzoom))
+        .filter(|(minz, maxz)| minz <= maxz);
+    for (minz, maxz) in lower.iter().chain(&higher) {
+        zoom.stats.total += GridIterator::new(*minz, *maxz, zoom.limits.clone()).len() as u64;
+    }
+    if let Some((minz, maxz)) = lower {
+        for tile in GridIterator::new(minz, minz, zoom.limits.clone()) {
+            zoom.underzoom(tile, maxz, options.source_minzoom);
+        }
+    }
+    if let Some((minz, maxz)) = higher {
+        zoom.overzoom(minz, maxz, options.source_maxzoom);
+    }
+    let res = dst
+        .stop_writing
+        .send(StopWriting)
+        .map_err(Error::from)
+        .and_then(|res| res)
+        .wait();
+    if let Err(e) = res {
+        error!("StopWriting: {}", e);
     }
+    Ok(zoom.stats)
 }
@@ -0,0 +1,776 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::blank::Blank;
+use crate::message::{
+    GetInfo, GetInfoResult, GetTile, GetTileResult, PutInfo, PutInfoResult, PutTile, PutTileResult,
+    SinkAddr, SourceAddr, StopWriting, StopWritingResult,
+};
+use crate::operation::{tile_copy, tile_copy_with_progress, tile_zoom, CopyOptions, ZoomOptions};
+use crate::pool::Pool;
+use crate::raster::{self, RasterFormat};
+use crate::scheme::Scheme;
+use crate::tile::Tile;
+use crate::tileinfo::TileInfo;
+use crate::transform::{Pipeline, Transform};
+use crate::Error;
+use ::actix::prelude::*;
+use legeo_xyz::grid::{Extent, Grid, Origin, Unit};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Source with tiles where x+y is even and a read error at 2/0/0
+struct CheckerSource;
+
+impl Actor for CheckerSource {
+    type Context = Context<Self>;
+}
+
+impl Handler<GetTile> for CheckerSource {
+    type Result = GetTileResult;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        if (msg.z, msg.x, msg.y) == (2, 0, 0) {
+            Err(Error::backend("read error"))
+        } else if (msg.x + msg.y).is_multiple_of(2) {
+            Ok(Some(Tile::new(
+                format!("{}/{}/{}", msg.z, msg.x, msg.y).into_bytes(),
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Handler<GetInfo> for CheckerSource {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        Ok(TileInfo {
+            name: Some("checker".to_string()),
+            bounds: Some([-180.0, -85.0511, 180.0, 85.0511]),
+            minzoom: Some(0),
+            maxzoom: Some(2),
+            ..Default::default()
+        })
+    }
+}
+
+/// Source failing on the first two requests of each tile
+#[derive(Default)]
+struct FlakySource {
+    attempts: HashMap<(u8, u32, u32), u32>,
+}
+
+impl Actor for FlakySource {
+    type Context = Context<Self>;
+}
+
+impl Handler<GetTile> for FlakySource {
+    type Result = GetTileResult;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        let attempts = self.attempts.entry((msg.z, msg.x, msg.y)).or_insert(0);
+        *attempts += 1;
+        if *attempts <= 2 {
+            Err(Error::backend("temporary error"))
+        } else {
+            Ok(Some(Tile::new(Vec::new())))
+        }
+    }
+}
+
+impl Handler<GetInfo> for FlakySource {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        Ok(TileInfo::default())
+    }
+}
+
+type TileList = Arc<Mutex<Vec<(u8, u32, u32)>>>;
+
+/// Sink collecting tile coordinates, committed with `StopWriting`
+struct MemorySink {
+    pending: Vec<(u8, u32, u32)>,
+    tiles: TileList,
+    info: Arc<Mutex<Option<TileInfo>>>,
+}
+
+fn memory_sink(tiles: &TileList) -> SinkAddr {
+    memory_sink_with_info(tiles, &Arc::new(Mutex::new(None)))
+}
+
+fn memory_sink_with_info(tiles: &TileList, info: &Arc<Mutex<Option<TileInfo>>>) -> SinkAddr {
+    let (tiles, info) = (tiles.clone(), info.clone());
+    SinkAddr::new(Arbiter::start(move |_| MemorySink {
+        pending: Vec::new(),
+        tiles,
+        info,
+    }))
+}
+
+impl Actor for MemorySink {
+    type Context = Context<Self>;
+}
+
+impl Handler<PutTile> for MemorySink {
+    type Result = PutTileResult;
+
+    fn handle(&mut self, msg: PutTile, _: &mut Context<Self>) -> Self::Result {
+        self.pending.push((msg.z, msg.x, msg.y));
+        Ok(())
+    }
+}
+
+impl Handler<PutInfo> for MemorySink {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        *self.info.lock().unwrap() = Some(msg.info);
+        Ok(())
+    }
+}
+
+impl Handler<StopWriting> for MemorySink {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        self.tiles.lock().unwrap().append(&mut self.pending);
+        Ok(())
+    }
+}
+
+fn options(concurrency: usize) -> CopyOptions {
+    CopyOptions {
+        minzoom: 0,
+        maxzoom: 2,
+        concurrency,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_copy_sparse() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let stats = tile_copy(src, dst, &options(1)).unwrap();
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (21, 10, 10, 1)
+        );
+        System::current().stop();
+    });
+    let tiles = tiles.lock().unwrap();
+    assert_eq!(tiles.len(), 10);
+    assert!(tiles.contains(&(1, 1, 1)));
+    assert!(!tiles.contains(&(1, 0, 1)));
+    assert!(!tiles.contains(&(2, 0, 0)));
+}
+
+#[test]
+fn test_copy_concurrent() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    let infos = vec![Arc::new(Mutex::new(None)), Arc::new(Mutex::new(None))];
+    let sink_infos = infos.clone();
+    System::run(move || {
+        let srcs = (0..3)
+            .map(|_| SourceAddr::new(Arbiter::start(|_| CheckerSource)))
+            .collect::<Vec<_>>();
+        let src = SourceAddr {
+            get_tile: Pool::start(srcs.iter().map(|s| s.get_tile.clone()).collect()),
+            get_info: Pool::start(srcs.into_iter().map(|s| s.get_info).collect()),
+        };
+        let sinks = sink_infos
+            .iter()
+            .map(|info| memory_sink_with_info(&sink_tiles, info))
+            .collect::<Vec<_>>();
+        let dst = SinkAddr {
+            put_tile: Pool::start(sinks.iter().map(|s| s.put_tile.clone()).collect()),
+            put_info: Pool::start(sinks.iter().map(|s| s.put_info.clone()).collect()),
+            stop_writing: Pool::start(sinks.into_iter().map(|s| s.stop_writing).collect()),
+        };
+        let stats = tile_copy(src, dst, &options(4)).unwrap();
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (21, 10, 10, 1)
+        );
+        System::current().stop();
+    });
+    let mut tiles = tiles.lock().unwrap().clone();
+    tiles.sort();
+    tiles.dedup();
+    assert_eq!(tiles.len(), 10);
+    // Every worker gets the tileset info
+    assert!(infos.iter().all(|info| info.lock().unwrap().is_some()));
+}
+
+#[test]
+fn test_copy_progress() {
+    System::run(|| {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&Arc::new(Mutex::new(Vec::new())));
+        let mut updates = Vec::new();
+        let stats = tile_copy_with_progress(src, dst, &options(2), |stats| {
+            updates.push(stats.processed())
+        })
+        .unwrap();
+        assert_eq!(updates, (1..=21).collect::<Vec<_>>());
+        assert_eq!(stats.bytes, 50);
+        assert_eq!(stats.eta(), Some(std::time::Duration::from_secs(0)));
+        System::current().stop();
+    });
+}
+
+#[test]
+fn test_copy_retry() {
+    let retry_options = |retry| CopyOptions {
+        retry,
+        retry_delay: Duration::from_millis(1),
+        ..options(4)
+    };
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| FlakySource::default()));
+        let dst = memory_sink(&Arc::new(Mutex::new(Vec::new())));
+        let stats = tile_copy(src, dst.clone(), &retry_options(2)).unwrap();
+        assert_eq!((stats.copied, stats.failed), (21, 0));
+
+        let src = SourceAddr::new(Arbiter::start(|_| FlakySource::default()));
+        let stats = tile_copy(src, dst.clone(), &retry_options(1)).unwrap();
+        assert_eq!((stats.copied, stats.failed), (0, 21));
+        assert_eq!(stats.failed_tiles.len(), 21);
+
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let stats = tile_copy(src, dst, &retry_options(3)).unwrap();
+        assert_eq!(stats.failed_tiles, vec![(2, 0, 0)]);
+        System::current().stop();
+    });
+}
+
+#[test]
+fn test_copy_parts() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let mut processed = 0;
+        for part in 0..3 {
+            let part_options = CopyOptions {
+                parts: 3,
+                part,
+                ..options(4)
+            };
+            let stats = tile_copy(src.clone(), dst.clone(), &part_options).unwrap();
+            assert_eq!(stats.total, 7);
+            processed += stats.processed();
+        }
+        assert_eq!(processed, 21);
+        System::current().stop();
+    });
+    let mut tiles = tiles.lock().unwrap().clone();
+    tiles.sort();
+    tiles.dedup();
+    assert_eq!(tiles.len(), 10);
+}
+
+#[test]
+fn test_copy_pyramid() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let pyramid_options = CopyOptions {
+            scheme: Scheme::Pyramid,
+            ..options(4)
+        };
+        let stats = tile_copy(src, dst, &pyramid_options).unwrap();
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (21, 6, 14, 1)
+        );
+        System::current().stop();
+    });
+    let mut tiles = tiles.lock().unwrap().clone();
+    tiles.sort();
+    assert_eq!(
+        tiles,
+        vec![
+            (0, 0, 0),
+            (1, 0, 0),
+            (1, 1, 1),
+            (2, 1, 1),
+            (2, 2, 2),
+            (2, 3, 3)
+        ]
+    );
+}
+
+#[test]
+fn test_copy_pyramid_failed() {
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&Arc::new(Mutex::new(Vec::new())));
+        let pyramid_options = CopyOptions {
+            scheme: Scheme::Pyramid,
+            maxzoom: 3,
+            ..options(4)
+        };
+        let stats = tile_copy(src, dst, &pyramid_options).unwrap();
+        // Read error at 2/0/0 and its 4 children
+        assert_eq!(stats.failed, 5);
+        assert_eq!(stats.failed_tiles, vec![(2, 0, 0)]);
+        assert_eq!(stats.processed(), stats.total);
+        System::current().stop();
+    });
+}
+
+#[test]
+fn test_copy_pyramid_parts() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        // Level 0 has less tiles than parts, so parts are split at level 1
+        let totals = (0..4)
+            .map(|part| {
+                let part_options = CopyOptions {
+                    scheme: Scheme::Pyramid,
+                    parts: 4,
+                    part,
+                    ..options(4)
+                };
+                tile_copy(src.clone(), dst.clone(), &part_options)
+                    .unwrap()
+                    .total
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(totals, vec![6, 5, 5, 5]);
+        System::current().stop();
+    });
+    let mut tiles = tiles.lock().unwrap().clone();
+    tiles.sort();
+    assert_eq!(
+        tiles,
+        vec![
+            (0, 0, 0),
+            (1, 0, 0),
+            (1, 1, 1),
+            (2, 1, 1),
+            (2, 2, 2),
+            (2, 3, 3)
+        ]
+    );
+}
+
+#[test]
+fn test_copy_bounds() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let north_east = Extent {
+            minx: 0.0,
+            miny: 0.0,
+            maxx: 180.0,
+            maxy: 85.0,
+        };
+        let stats = tile_copy(
+            src.clone(),
+            dst.clone(),
+            &CopyOptions {
+                bounds: north_east.clone(),
+                ..options(4)
+            },
+        )
+        .unwrap();
+        assert_eq!((stats.total, stats.copied), (6, 3));
+        // WGS84 grid with 2x1 tiles at level 0
+        let stats = tile_copy(
+            src,
+            dst,
+            &CopyOptions {
+                grid: Grid::wgs84(),
+                bounds: Extent {
+                    maxy: 90.0,
+                    ..north_east
+                },
+                maxzoom: 1,
+                ..options(4)
+            },
+        )
+        .unwrap();
+        assert_eq!((stats.total, stats.copied), (3, 1));
+        System::current().stop();
+    });
+    let mut tiles = tiles.lock().unwrap().clone();
+    tiles.sort();
+    // XYZ rows of the northern hemisphere
+    assert_eq!(tiles, vec![(0, 0, 0), (1, 2, 0), (2, 2, 0), (2, 3, 1)]);
+}
+
+#[test]
+fn test_copy_projected_grid() {
+    let extent = Extent {
+        minx: 2420000.0,
+        miny: 1030000.0,
+        maxx: 2900000.0,
+        maxy: 1350000.0,
+    };
+    // 1000 -> 750 is no quadtree level
+    let grid = Grid::builder(extent.clone(), 2056, Unit::Meters)
+        .origin(Origin::TopLeft)
+        .resolutions(vec![4000.0, 1000.0, 750.0])
+        .build()
+        .unwrap();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&Arc::new(Mutex::new(Vec::new())));
+        let grid_options = CopyOptions { grid, ..options(4) };
+        match tile_copy(src.clone(), dst.clone(), &grid_options) {
+            Err(Error::UnsupportedParameter { name, .. }) => assert_eq!(name, "bounds"),
+            _ => panic!("UnsupportedParameter expected"),
+        }
+        let grid_options = CopyOptions {
+            grid_bounds: Some(extent),
+            ..grid_options
+        };
+        let stats = tile_copy(src.clone(), dst.clone(), &grid_options).unwrap();
+        assert_eq!(stats.total, 1 + 2 * 2 + 3 * 2);
+        let pyramid_options = CopyOptions {
+            scheme: Scheme::Pyramid,
+            ..grid_options
+        };
+        match tile_copy(src, dst, &pyramid_options) {
+            Err(Error::UnsupportedParameter { name, .. }) => assert_eq!(name, "scheme"),
+            _ => panic!("UnsupportedParameter expected"),
+        }
+        System::current().stop();
+    });
+}
+
+#[test]
+fn test_copy_list() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let list_options = CopyOptions {
+            scheme: Scheme::List(vec![(5, 3, 3), (5, 3, 4), (1, 1, 1)]),
+            ..options(1)
+        };
+        let stats = tile_copy(src, dst, &list_options).unwrap();
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (3, 2, 1, 0)
+        );
+        System::current().stop();
+    });
+    assert_eq!(*tiles.lock().unwrap(), vec![(5, 3, 3), (1, 1, 1)]);
+}
+
+#[test]
+fn test_copy_info() {
+    let info = Arc::new(Mutex::new(None));
+    let sink_info = info.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink_with_info(&Arc::new(Mutex::new(Vec::new())), &sink_info);
+        let info_options = CopyOptions {
+            bounds: Extent {
+                minx: 0.0,
+                miny: 0.0,
+                maxx: 180.0,
+                maxy: 90.0,
+            },
+            minzoom: 1,
+            maxzoom: 4,
+            ..options(1)
+        };
+        tile_copy(src, dst, &info_options).unwrap();
+        System::current().stop();
+    });
+    let info = info.lock().unwrap().clone().unwrap();
+    assert_eq!(info.name, Some("checker".to_string()));
+    assert_eq!(info.minzoom, Some(1));
+    assert_eq!(info.maxzoom, Some(2));
+    assert_eq!(info.bounds, Some([0.0, 0.0, 180.0, 85.0511]));
+}
+
+/// Transform failing on zoom level 1 and renaming the tileset
+#[derive(Debug)]
+struct FailingTransform;
+
+impl Transform for FailingTransform {
+    fn transform(&self, z: u8, _x: u32, _y: u32, data: Vec<u8>) -> crate::Result<Vec<u8>> {
+        if z == 1 {
+            Err(Error::backend("transform error"))
+        } else {
+            Ok(data)
+        }
+    }
+
+    fn transform_info(&self, info: TileInfo) -> TileInfo {
+        TileInfo {
+            name: Some("transformed".to_string()),
+            ..info
+        }
+    }
+}
+
+#[test]
+fn test_copy_transform() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let info = Arc::new(Mutex::new(None));
+    let (sink_tiles, sink_info) = (tiles.clone(), info.clone());
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink_with_info(&sink_tiles, &sink_info);
+        let transform_options = CopyOptions {
+            transforms: Pipeline::from(vec![Arc::new(FailingTransform) as Arc<dyn Transform>]),
+            ..options(4)
+        };
+        let stats = tile_copy(src, dst, &transform_options).unwrap();
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (21, 8, 10, 3)
+        );
+        System::current().stop();
+    });
+    assert!(tiles.lock().unwrap().iter().all(|tile| tile.0 != 1));
+    let info = info.lock().unwrap().clone().unwrap();
+    assert_eq!(info.name, Some("transformed".to_string()));
+}
+
+/// Transform emptying tiles of zoom level 2
+#[derive(Debug)]
+struct EmptyTransform;
+
+impl Transform for EmptyTransform {
+    fn transform(&self, z: u8, _x: u32, _y: u32, data: Vec<u8>) -> crate::Result<Vec<u8>> {
+        Ok(if z == 2 { Vec::new() } else { data })
+    }
+}
+
+#[test]
+fn test_copy_blank() {
+    let tiles = Arc::new(Mutex::new(Vec::new()));
+    let sink_tiles = tiles.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| CheckerSource));
+        let dst = memory_sink(&sink_tiles);
+        let blank_options = |skip_blank| CopyOptions {
+            transforms: Pipeline::from(vec![Arc::new(EmptyTransform) as Arc<dyn Transform>]),
+            blank: Some(Blank::Uniform { tolerance: 0 }),
+            skip_blank,
+            blank_list: skip_blank,
+            ..options(4)
+        };
+        let stats = tile_copy(src.clone(), dst.clone(), &blank_options(false)).unwrap();
+        assert_eq!(
+            (stats.copied, stats.skipped, stats.failed, stats.blank),
+            (10, 10, 1, 7)
+        );
+        assert!(stats.blank_tiles.is_empty());
+        let stats = tile_copy(src, dst, &blank_options(true)).unwrap();
+        assert_eq!(
+            (stats.copied, stats.skipped, stats.failed, stats.blank),
+            (3, 17, 1, 7)
+        );
+        let mut blank_tiles = stats.blank_tiles;
+        blank_tiles.sort();
+        assert_eq!(blank_tiles[0], (2, 0, 2));
+        System::current().stop();
+    });
+    assert_eq!(tiles.lock().unwrap().len(), 13);
+}
+
+/// Source with uniformly colored 8x8 PNG tiles at zoom level 2, except 2/3/3
+#[derive(Default)]
+struct RasterSource {
+    requests: TileList,
+}
+
+impl Actor for RasterSource {
+    type Context = Context<Self>;
+}
+
+/// Color of tile `x`/`y`
+fn tile_color(x: u32, y: u32) -> [u8; 4] {
+    [x as u8 * 80, y as u8 * 80, 0, 255]
+}
+
+impl Handler<GetTile> for RasterSource {
+    type Result = GetTileResult;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        self.requests.lock().unwrap().push((msg.z, msg.x, msg.y));
+        if msg.z != 2 || (msg.x, msg.y) == (3, 3) {
+            return Ok(None);
+        }
+        let img = image::RgbaImage::from_pixel(8, 8, image::Rgba(tile_color(msg.x, msg.y)));
+        let data = raster::encode(
+            &image::DynamicImage::ImageRgba8(img),
+            RasterFormat::Png { colors: None },
+        )?;
+        Ok(Some(Tile::new(data)))
+    }
+}
+
+impl Handler<GetInfo> for RasterSource {
+    type Result = GetInfoResult;
+
+    fn handle(&mut self, _msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        Ok(TileInfo {
+            format: Some("png".to_string()),
+            minzoom: Some(2),
+            maxzoom: Some(2),
+            ..Default::default()
+        })
+    }
+}
+
+type ImageTiles = Arc<Mutex<HashMap<(u8, u32, u32), Vec<u8>>>>;
+
+/// Sink storing tile data
+struct ImageSink {
+    tiles: ImageTiles,
+    info: Arc<Mutex<Option<TileInfo>>>,
+}
+
+impl Actor for ImageSink {
+    type Context = Context<Self>;
+}
+
+impl Handler<PutTile> for ImageSink {
+    type Result = PutTileResult;
+
+    fn handle(&mut self, msg: PutTile, _: &mut Context<Self>) -> Self::Result {
+        self.tiles
+            .lock()
+            .unwrap()
+            .insert((msg.z, msg.x, msg.y), msg.data);
+        Ok(())
+    }
+}
+
+impl Handler<PutInfo> for ImageSink {
+    type Result = PutInfoResult;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        *self.info.lock().unwrap() = Some(msg.info);
+        Ok(())
+    }
+}
+
+impl Handler<StopWriting> for ImageSink {
+    type Result = StopWritingResult;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_zoom() {
+    let tiles = Arc::new(Mutex::new(HashMap::new()));
+    let info = Arc::new(Mutex::new(None));
+    let (sink_tiles, sink_info) = (tiles.clone(), info.clone());
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(|_| RasterSource::default()));
+        let dst = SinkAddr::new(Arbiter::start(move |_| ImageSink {
+            tiles: sink_tiles,
+            info: sink_info,
+        }));
+        let options = ZoomOptions {
+            minzoom: 0,
+            maxzoom: 3,
+            source_minzoom: 2,
+            source_maxzoom: 2,
+            ..Default::default()
+        };
+        let stats = tile_zoom(src, dst, &options).unwrap();
+        // Children of missing tile 2/3/3 are skipped
+        assert_eq!(
+            (stats.total, stats.copied, stats.skipped, stats.failed),
+            (69, 65, 4, 0)
+        );
+        System::current().stop();
+    });
+    let info = info.lock().unwrap().clone().unwrap();
+    assert_eq!((info.minzoom, info.maxzoom), (Some(0), Some(3)));
+
+    let tiles = tiles.lock().unwrap();
+    assert_eq!(tiles.len(), 65);
+    let image = |tile| {
+        let (img, _) = raster::decode(&tiles[&tile]).unwrap().unwrap();
+        img.to_rgba8()
+    };
+    let close = |a: [u8; 4], b: [u8; 4]| a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 8);
+
+    // Upscaled from parent 2/2/1
+    let img = image((3, 5, 2));
+    assert_eq!(img.dimensions(), (8, 8));
+    assert!(img.pixels().all(|p| p.0 == tile_color(2, 1)));
+
+    // Mosaic of 2/2/0, 2/2/1, 2/3/0, 2/3/1
+    let img = image((1, 1, 0));
+    assert_eq!(img.dimensions(), (8, 8));
+    assert!(close(img.get_pixel(0, 0).0, tile_color(2, 0)));
+    assert!(close(img.get_pixel(7, 0).0, tile_color(3, 0)));
+    assert!(close(img.get_pixel(0, 7).0, tile_color(2, 1)));
+    assert!(close(img.get_pixel(7, 7).0, tile_color(3, 1)));
+    // Missing 2/3/3 is transparent
+    let img = image((1, 1, 1));
+    assert_eq!(img.get_pixel(7, 7).0[3], 0);
+
+    let img = image((0, 0, 0));
+    assert!(close(img.get_pixel(0, 0).0, tile_color(0, 0)));
+    assert!(close(img.get_pixel(7, 0).0, tile_color(3, 0)));
+}
+
+#[test]
+fn test_zoom_bounds() {
+    let requests = TileList::default();
+    let src_requests = requests.clone();
+    System::run(move || {
+        let src = SourceAddr::new(Arbiter::start(move |_| RasterSource {
+            requests: src_requests,
+        }));
+        let dst = SinkAddr::new(Arbiter::start(|_| ImageSink {
+            tiles: Default::default(),
+            info: Default::default(),
+        }));
+        let options = ZoomOptions {
+            bounds: Extent {
+                minx: 0.0,
+                miny: 0.0,
+                maxx: 180.0,
+                maxy: 85.0,
+            },
+            minzoom: 0,
+            maxzoom: 1,
+            source_minzoom: 2,
+            source_maxzoom: 2,
+            ..Default::default()
+        };
+        let stats = tile_zoom(src, dst, &options).unwrap();
+        assert_eq!((stats.total, stats.copied), (2, 2));
+        System::current().stop();
+    });
+    // Only source tiles within bounds are read
+    let mut requests = requests.lock().unwrap().clone();
+    requests.sort();
+    assert_eq!(requests, vec![(2, 2, 0), (2, 2, 1), (2, 3, 0), (2, 3, 1)]);
+}
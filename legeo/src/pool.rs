@@ -0,0 +1,134 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Actor pools
+
+use crate::error::Error;
+use crate::message::{GetInfo, GetTile, PutInfo, PutTile, StopWriting};
+use crate::tile::Tile;
+use crate::tileinfo::TileInfo;
+use ::actix::prelude::*;
+use futures::{future, Future};
+
+/// Distributes messages round-robin to a set of worker actors.
+/// `PutInfo` and `StopWriting` are sent to all workers.
+pub struct Pool<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    workers: Vec<Recipient<M>>,
+    next: usize,
+}
+
+impl<M> Pool<M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+    Pool<M>: Actor<Context = Context<Pool<M>>> + Handler<M>,
+{
+    /// Start pool in its own arbiter. A single worker is returned unchanged.
+    pub fn start(mut workers: Vec<Recipient<M>>) -> Recipient<M> {
+        if workers.len() == 1 {
+            return workers.remove(0);
+        }
+        Arbiter::start(move |_| Pool { workers, next: 0 }).recipient()
+    }
+}
+
+impl<M> Pool<M>
+where
+    M: Message + Send,
+    M::Result: Send,
+{
+    fn next_worker(&mut self) -> &Recipient<M> {
+        let worker = &self.workers[self.next];
+        self.next = (self.next + 1) % self.workers.len();
+        worker
+    }
+}
+
+impl<M> Actor for Pool<M>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+{
+    type Context = Context<Self>;
+}
+
+impl Handler<GetTile> for Pool<GetTile> {
+    type Result = ResponseFuture<Option<Tile>, Error>;
+
+    fn handle(&mut self, msg: GetTile, _: &mut Context<Self>) -> Self::Result {
+        Box::new(
+            self.next_worker()
+                .send(msg)
+                .map_err(Error::from)
+                .and_then(|res| res),
+        )
+    }
+}
+
+impl Handler<PutTile> for Pool<PutTile> {
+    type Result = ResponseFuture<(), Error>;
+
+    fn handle(&mut self, msg: PutTile, _: &mut Context<Self>) -> Self::Result {
+        Box::new(
+            self.next_worker()
+                .send(msg)
+                .map_err(Error::from)
+                .and_then(|res| res),
+        )
+    }
+}
+
+impl Handler<GetInfo> for Pool<GetInfo> {
+    type Result = ResponseFuture<TileInfo, Error>;
+
+    fn handle(&mut self, msg: GetInfo, _: &mut Context<Self>) -> Self::Result {
+        Box::new(
+            self.next_worker()
+                .send(msg)
+                .map_err(Error::from)
+                .and_then(|res| res),
+        )
+    }
+}
+
+impl Handler<PutInfo> for Pool<PutInfo> {
+    type Result = ResponseFuture<(), Error>;
+
+    fn handle(&mut self, msg: PutInfo, _: &mut Context<Self>) -> Self::Result {
+        let requests = self
+            .workers
+            .iter()
+            .map(|worker| {
+                worker
+                    .send(msg.clone())
+                    .map_err(Error::from)
+                    .and_then(|res| res)
+            })
+            .collect::<Vec<_>>();
+        Box::new(future::join_all(requests).map(|_| ()))
+    }
+}
+
+impl Handler<StopWriting> for Pool<StopWriting> {
+    type Result = ResponseFuture<(), Error>;
+
+    fn handle(&mut self, _msg: StopWriting, _: &mut Context<Self>) -> Self::Result {
+        let requests = self
+            .workers
+            .iter()
+            .map(|worker| {
+                worker
+                    .send(StopWriting)
+                    .map_err(Error::from)
+                    .and_then(|res| res)
+            })
+            .collect::<Vec<_>>();
+        Box::new(future::join_all(requests).map(|_| ()))
+    }
+}
@@ -0,0 +1,302 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Raster tile re-encoding
+
+use crate::error::{Error, Result};
+use crate::tileinfo::TileInfo;
+use crate::tiletype::{self, TileFormat};
+use crate::transform::Transform;
+use color_quant::NeuQuant;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use std::io::Cursor;
+use std::str::FromStr;
+
+/// Default JPEG quality
+const JPEG_QUALITY: u8 = 85;
+/// Default lossy WebP quality
+const WEBP_QUALITY: u8 = 80;
+
+/// Raster output format with encoding options
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RasterFormat {
+    /// PNG, quantized to an 8-bit palette with the given number of colors if set
+    Png { colors: Option<u16> },
+    /// JPEG with quality 1-100
+    Jpeg { quality: u8 },
+    /// WebP with quality 0-100, lossless if `None`
+    Webp { quality: Option<u8> },
+}
+
+impl RasterFormat {
+    pub fn format(&self) -> TileFormat {
+        match self {
+            RasterFormat::Png { .. } => TileFormat::Png,
+            RasterFormat::Jpeg { .. } => TileFormat::Jpeg,
+            RasterFormat::Webp { .. } => TileFormat::Webp,
+        }
+    }
+
+    /// Output format with default options for tiles of `format`
+    pub fn from_format(format: TileFormat) -> RasterFormat {
+        match format {
+            TileFormat::Jpeg => RasterFormat::Jpeg {
+                quality: JPEG_QUALITY,
+            },
+            TileFormat::Webp => RasterFormat::Webp {
+                quality: Some(WEBP_QUALITY),
+            },
+            _ => RasterFormat::Png { colors: None },
+        }
+    }
+}
+
+impl FromStr for RasterFormat {
+    type Err = String;
+
+    /// Parse `png[:colors]`, `jpeg[:quality]` or `webp[:quality|lossless]`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, option) = match s.find(':') {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        };
+        let number = |max: u16| -> std::result::Result<Option<u16>, String> {
+            match option {
+                None => Ok(None),
+                Some(value) => match value.parse() {
+                    Ok(n) if n <= max => Ok(Some(n)),
+                    _ => Err(format!("Invalid option `{}` for `{}`", value, name)),
+                },
+            }
+        };
+        match name {
+            "png" => match number(256)? {
+                Some(colors) if colors < 2 => Err(format!("Invalid option `{}` for `png`", colors)),
+                colors => Ok(RasterFormat::Png { colors }),
+            },
+            "jpeg" | "jpg" => Ok(RasterFormat::Jpeg {
+                quality: number(100)?.map_or(JPEG_QUALITY, |q| q.max(1) as u8),
+            }),
+            "webp" if option == Some("lossless") => Ok(RasterFormat::Webp { quality: None }),
+            "webp" => Ok(RasterFormat::Webp {
+                quality: Some(number(100)?.map_or(WEBP_QUALITY, |q| q as u8)),
+            }),
+            _ => Err(format!("Unknown raster format `{}`", name)),
+        }
+    }
+}
+
+/// Decode raster tiles and encode them in another format.
+/// Empty tiles are kept empty, other non-raster tiles fail.
+#[derive(Debug)]
+pub struct Reencode(pub RasterFormat);
+
+impl Transform for Reencode {
+    fn transform(&self, _z: u8, _x: u32, _y: u32, data: Vec<u8>) -> Result<Vec<u8>> {
+        if data.is_empty() {
+            return Ok(data);
+        }
+        match tiletype::detect(&data).format {
+            TileFormat::Png | TileFormat::Jpeg | TileFormat::Webp | TileFormat::Gif => {}
+            format => {
+                return Err(Error::backend(format!(
+                    "Can't re-encode {} tile",
+                    format.name()
+                )))
+            }
+        }
+        let img = image::load_from_memory(&data).map_err(Error::backend)?;
+        encode(&img, self.0)
+    }
+
+    /// Set `format` to the output format
+    fn transform_info(&self, info: TileInfo) -> TileInfo {
+        TileInfo {
+            format: Some(self.0.format().name().to_string()),
+            ..info
+        }
+    }
+}
+
+/// Encode image in raster format
+pub fn encode(img: &DynamicImage, format: RasterFormat) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match format {
+        RasterFormat::Png { colors: None } => {
+            img.write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+                .map_err(Error::backend)?;
+        }
+        RasterFormat::Png {
+            colors: Some(colors),
+        } => buf = quantized_png(img, usize::from(colors))?,
+        RasterFormat::Jpeg { quality } => {
+            // JPEG has no alpha channel
+            let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+            JpegEncoder::new_with_quality(&mut buf, quality)
+                .encode_image(&rgb)
+                .map_err(Error::backend)?;
+        }
+        RasterFormat::Webp { quality } => {
+            let rgba = img.to_rgba8();
+            let encoder = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height());
+            let webp = match quality {
+                Some(quality) => encoder.encode(f32::from(quality)),
+                None => encoder.encode_lossless(),
+            };
+            buf.extend_from_slice(&webp);
+        }
+    }
+    Ok(buf)
+}
+
+/// Decode raster tile. Returns `None` for non-raster tiles.
+pub fn decode(data: &[u8]) -> Option<Result<(DynamicImage, TileFormat)>> {
+    match tiletype::detect(data).format {
+        format @ (TileFormat::Png | TileFormat::Jpeg | TileFormat::Webp | TileFormat::Gif) => Some(
+            image::load_from_memory(data)
+                .map(|img| (img, format))
+                .map_err(Error::backend),
+        ),
+        _ => None,
+    }
+}
+
+/// Downsampled 2x2 mosaic of `tiles` with `(column, row, image)`.
+/// Missing tiles are transparent.
+pub fn mosaic(tiles: &[(u32, u32, DynamicImage)], width: u32, height: u32) -> DynamicImage {
+    let mut canvas = RgbaImage::new(width * 2, height * 2);
+    for (column, row, img) in tiles {
+        let img = img.resize_exact(width, height, FilterType::Triangle);
+        imageops::replace(
+            &mut canvas,
+            &img.to_rgba8(),
+            i64::from(column * width),
+            i64::from(row * height),
+        );
+    }
+    DynamicImage::ImageRgba8(canvas).resize_exact(width, height, FilterType::Triangle)
+}
+
+/// Part of `img` at pixel position `(x, y)` with size `(width, height)`,
+/// scaled to the size of `img`
+pub fn upscale(img: &DynamicImage, x: u32, y: u32, width: u32, height: u32) -> DynamicImage {
+    img.crop_imm(x, y, width.max(1), height.max(1))
+        .resize_exact(img.width(), img.height(), FilterType::Triangle)
+}
+
+/// 8-bit palette PNG with at most `colors` colors
+fn quantized_png(img: &DynamicImage, colors: usize) -> Result<Vec<u8>> {
+    let rgba = img.to_rgba8();
+    let quant = NeuQuant::new(10, colors, rgba.as_raw());
+    let indices = rgba
+        .pixels()
+        .map(|pixel| quant.index_of(&pixel.0) as u8)
+        .collect::<Vec<_>>();
+    let palette = quant.color_map_rgba();
+    let rgb = palette
+        .chunks(4)
+        .flat_map(|c| c[..3].to_vec())
+        .collect::<Vec<_>>();
+    let alpha = palette.chunks(4).map(|c| c[3]).collect::<Vec<_>>();
+
+    let mut buf = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buf, rgba.width(), rgba.height());
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(rgb);
+    encoder.set_trns(alpha);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&indices))
+        .map_err(Error::backend)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+fn test_png() -> Vec<u8> {
+    let img = image::RgbaImage::from_fn(256, 256, |x, y| {
+        image::Rgba([x as u8, y as u8, 128, if x < 128 { 255 } else { 64 }])
+    });
+    encode(
+        &DynamicImage::ImageRgba8(img),
+        RasterFormat::Png { colors: None },
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_parse_format() {
+    assert_eq!("png".parse(), Ok(RasterFormat::Png { colors: None }));
+    assert_eq!("png:64".parse(), Ok(RasterFormat::Png { colors: Some(64) }));
+    assert_eq!("jpg".parse(), Ok(RasterFormat::Jpeg { quality: 85 }));
+    assert_eq!("jpeg:60".parse(), Ok(RasterFormat::Jpeg { quality: 60 }));
+    assert_eq!("webp".parse(), Ok(RasterFormat::Webp { quality: Some(80) }));
+    assert_eq!(
+        "webp:lossless".parse(),
+        Ok(RasterFormat::Webp { quality: None })
+    );
+    assert!("png:1".parse::<RasterFormat>().is_err());
+    assert!("jpeg:101".parse::<RasterFormat>().is_err());
+    assert!("tiff".parse::<RasterFormat>().is_err());
+}
+
+#[test]
+fn test_reencode() {
+    let png = test_png();
+    for spec in &["jpeg:60", "webp", "webp:lossless", "png:16", "png"] {
+        let format = spec.parse::<RasterFormat>().unwrap();
+        let data = Reencode(format).transform(0, 0, 0, png.clone()).unwrap();
+        assert_eq!(tiletype::detect(&data).format, format.format());
+        let img = image::load_from_memory(&data).unwrap();
+        assert_eq!((img.width(), img.height()), (256, 256));
+    }
+
+    let quantized = Reencode(RasterFormat::Png { colors: Some(16) })
+        .transform(0, 0, 0, png.clone())
+        .unwrap();
+    let decoder = png::Decoder::new(&quantized[..]);
+    let reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+    assert!(reader.info().palette.as_ref().unwrap().len() <= 16 * 3);
+
+    let (img, format) = decode(&png).unwrap().unwrap();
+    assert_eq!(format, TileFormat::Png);
+    assert_eq!((img.width(), img.height()), (256, 256));
+    assert!(decode(b"\x1a\x04\x0a\x02id").is_none());
+
+    let info = Reencode(RasterFormat::Webp { quality: None }).transform_info(TileInfo {
+        format: Some("png".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(info.format, Some("webp".to_string()));
+
+    let reencode = Reencode(RasterFormat::Jpeg { quality: 85 });
+    assert!(reencode
+        .transform(0, 0, 0, b"\x1a\x04\x0a\x02id".to_vec())
+        .is_err());
+    assert!(reencode.transform(0, 0, 0, Vec::new()).unwrap().is_empty());
+}
+
+#[test]
+fn test_mosaic_upscale() {
+    let red = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255])));
+    let img = mosaic(&[(1, 0, red.clone())], 4, 4).to_rgba8();
+    assert_eq!(img.dimensions(), (4, 4));
+    assert_eq!(img.get_pixel(3, 0).0, [255, 0, 0, 255]);
+    assert_eq!(img.get_pixel(0, 3).0[3], 0);
+
+    let quadrants = RgbaImage::from_fn(4, 4, |x, y| {
+        if x >= 2 && y >= 2 {
+            image::Rgba([0, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 0])
+        }
+    });
+    let img = upscale(&DynamicImage::ImageRgba8(quadrants), 2, 2, 2, 2).to_rgba8();
+    assert_eq!(img.dimensions(), (4, 4));
+    assert!(img.pixels().all(|p| p.0 == [0, 0, 255, 255]));
+}
@@ -0,0 +1,173 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tilesource/Tilesink registry
+
+use crate::error::{Error, Result};
+use crate::message::{GetInfo, GetTile, PutInfo, PutTile, SinkAddr, SourceAddr, StopWriting};
+use crate::pool::Pool;
+use crate::tilesink::Tilesink;
+use crate::tilesource::Tilesource;
+use ::actix::prelude::*;
+use log::info;
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+//  From https://github.com/mapbox/tilelive/blob/master/lib/tilelive.js
+//
+// * `tilelive.protocols`: Hash of registered protocols, e.g. `tilelive.protocols["mbtiles:"] = MBTiles`.
+//   Backends register themselves with `registerProtocols(tilelive)`.
+
+/// Starts a source actor for a URI
+pub type SourceFactory = Box<dyn Fn(&str) -> Result<SourceAddr>>;
+
+/// Starts a sink actor for a URI
+pub type SinkFactory = Box<dyn Fn(&str) -> Result<SinkAddr>>;
+
+/// URI scheme registry for source and sink actors
+#[derive(Default)]
+pub struct Registry {
+    sources: HashMap<String, SourceFactory>,
+    sinks: HashMap<String, SinkFactory>,
+    single_writers: HashSet<String>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Register a source factory for `scheme`
+    pub fn add_source(&mut self, scheme: &str, factory: SourceFactory) {
+        self.sources.insert(scheme.to_string(), factory);
+    }
+
+    /// Register a sink factory for `scheme`
+    pub fn add_sink(&mut self, scheme: &str, factory: SinkFactory) {
+        self.sinks.insert(scheme.to_string(), factory);
+    }
+
+    /// Register a Tilesource actor started in its own arbiter for `scheme`
+    pub fn register_source<T>(&mut self, scheme: &str)
+    where
+        T: Tilesource + Actor<Context = Context<T>> + Handler<GetTile> + Handler<GetInfo> + Send,
+    {
+        self.add_source(
+            scheme,
+            Box::new(|uri| {
+                let backend = T::load(uri)?;
+                Ok(SourceAddr::new(Arbiter::start(move |_| backend)))
+            }),
+        );
+    }
+
+    /// Register a Tilesink actor started in its own arbiter for `scheme`.
+    /// Pools of single writer sinks are limited to one worker.
+    pub fn register_sink<T>(&mut self, scheme: &str)
+    where
+        T: Tilesink
+            + Actor<Context = Context<T>>
+            + Handler<PutTile>
+            + Handler<PutInfo>
+            + Handler<StopWriting>
+            + Send,
+    {
+        self.add_sink(
+            scheme,
+            Box::new(|uri| {
+                let backend = T::load(uri)?;
+                Ok(SinkAddr::new(Arbiter::start(move |_| backend)))
+            }),
+        );
+        if T::single_writer() {
+            self.single_writers.insert(scheme.to_string());
+        }
+    }
+
+    /// Registered source schemes
+    pub fn source_schemes(&self) -> Vec<&str> {
+        self.sources.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Registered sink schemes
+    pub fn sink_schemes(&self) -> Vec<&str> {
+        self.sinks.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Load source and start its actor. Has to be called within a running actix system.
+    pub fn source(&self, uri: &str) -> Result<SourceAddr> {
+        let scheme = scheme(uri)?;
+        let factory = self
+            .sources
+            .get(&scheme)
+            .ok_or(Error::UnknownScheme(scheme))?;
+        factory(uri)
+    }
+
+    /// Start `workers` source actors behind a round-robin pool
+    pub fn source_pool(&self, uri: &str, workers: usize) -> Result<SourceAddr> {
+        let workers = (0..workers.max(1))
+            .map(|_| self.source(uri))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(SourceAddr {
+            get_tile: Pool::start(workers.iter().map(|w| w.get_tile.clone()).collect()),
+            get_info: Pool::start(workers.into_iter().map(|w| w.get_info).collect()),
+        })
+    }
+
+    /// Load sink and start its actor. Has to be called within a running actix system.
+    pub fn sink(&self, uri: &str) -> Result<SinkAddr> {
+        let scheme = scheme(uri)?;
+        let factory = self
+            .sinks
+            .get(&scheme)
+            .ok_or(Error::UnknownScheme(scheme))?;
+        factory(uri)
+    }
+
+    /// Start `workers` sink actors behind a round-robin pool.
+    /// Single writer sinks are started with one worker.
+    pub fn sink_pool(&self, uri: &str, workers: usize) -> Result<SinkAddr> {
+        let workers = if workers > 1 && self.single_writers.contains(&scheme(uri)?) {
+            info!("Writing `{}` with a single worker", uri);
+            1
+        } else {
+            workers
+        };
+        let workers = (0..workers.max(1))
+            .map(|_| self.sink(uri))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(SinkAddr {
+            put_tile: Pool::start(workers.iter().map(|w| w.put_tile.clone()).collect()),
+            put_info: Pool::start(workers.iter().map(|w| w.put_info.clone()).collect()),
+            stop_writing: Pool::start(workers.into_iter().map(|w| w.stop_writing).collect()),
+        })
+    }
+}
+
+fn scheme(uri: &str) -> Result<String> {
+    // URL templates may contain placeholders which are invalid in URLs
+    match uri.find("://") {
+        Some(pos) => Ok(uri[..pos].to_string()),
+        None => Ok(Url::parse(uri)?.scheme().to_string()),
+    }
+}
+
+#[test]
+fn test_unknown_scheme() {
+    let registry = Registry::new();
+    match registry.source("mbtiles:///tmp/legeo.mbtiles") {
+        Err(Error::UnknownScheme(scheme)) => assert_eq!(scheme, "mbtiles"),
+        _ => panic!("UnknownScheme expected"),
+    }
+    match registry.source("https://{s}.tile.example.com/{z}/{x}/{y}.png") {
+        Err(Error::UnknownScheme(scheme)) => assert_eq!(scheme, "https"),
+        _ => panic!("UnknownScheme expected"),
+    }
+    match registry.sink("/tmp/legeo") {
+        Err(Error::InvalidUri(_)) => {}
+        _ => panic!("InvalidUri expected"),
+    }
+}
@@ -0,0 +1,157 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile iteration schemes
+
+use crate::error::{Error, Result};
+use legeo_xyz::grid::{ExtentInt, Grid};
+use std::io::BufRead;
+use std::str::FromStr;
+
+//  From https://github.com/mapbox/tilelive/blob/master/README.md
+//
+// * **--scheme**=[scanline,pyramid,list] - Default: scanline.
+// * **--list**=[filepath] - Filepath if scheme is list.
+
+/// Order of tiles processed by an operation
+#[derive(Clone, PartialEq, Default, Debug)]
+pub enum Scheme {
+    /// Level by level, column by column
+    #[default]
+    Scanline,
+    /// Level by level, parents before children.
+    /// Children of missing or empty tiles are skipped.
+    Pyramid,
+    /// Tiles `(z, x, y)` in list order
+    List(Vec<(u8, u32, u32)>),
+}
+
+impl FromStr for Scheme {
+    type Err = String;
+
+    /// Parse scheme name. The tiles of a `list` scheme have to be read separately.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "scanline" => Ok(Scheme::Scanline),
+            "pyramid" => Ok(Scheme::Pyramid),
+            "list" => Ok(Scheme::List(Vec::new())),
+            _ => Err(format!("Unknown scheme `{}`", s)),
+        }
+    }
+}
+
+/// Read tile list with one `z/x/y` entry per line. Empty lines are ignored.
+pub fn read_tile_list<R: BufRead>(reader: R) -> Result<Vec<(u8, u32, u32)>> {
+    let mut tiles = Vec::new();
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let tile = parse_tile(line).ok_or_else(|| {
+            Error::parse(format!("Invalid tile `{}` in line {}", line, lineno + 1))
+        })?;
+        tiles.push(tile);
+    }
+    Ok(tiles)
+}
+
+fn parse_tile(s: &str) -> Option<(u8, u32, u32)> {
+    let mut parts = s.split('/');
+    let z = parts.next()?.parse().ok()?;
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((z, x, y))
+}
+
+/// Children of tile `(z, x, y)` in `grid` within `limits`
+pub(crate) fn children<'a>(
+    grid: &Grid,
+    limits: &'a [ExtentInt],
+    (z, x, y): (u8, u32, u32),
+) -> impl Iterator<Item = (u8, u32, u32)> + 'a {
+    grid.children(x, y, z)
+        .into_iter()
+        .map(|(cx, cy, cz)| (cz, cx, cy))
+        .filter(move |&(cz, cx, cy)| {
+            limits.get(cz as usize).is_some_and(|limit| {
+                cx >= limit.minx && cx < limit.maxx && cy >= limit.miny && cy < limit.maxy
+            })
+        })
+}
+
+/// Number of descendants of tile `(z, x, y)` in `grid` within `limits` up to `maxz`
+pub(crate) fn descendant_count(
+    grid: &Grid,
+    limits: &[ExtentInt],
+    (z, x, y): (u8, u32, u32),
+    maxz: u8,
+) -> u64 {
+    let overlap = |min: u32, max: u32, lmin: u32, lmax: u32| {
+        u64::from(max.min(lmax).saturating_sub(min.max(lmin)))
+    };
+    (z + 1..=maxz)
+        .filter_map(|level| {
+            let limit = limits.get(level as usize)?;
+            let range = grid.descendants(x, y, z, level)?;
+            Some(
+                overlap(range.minx, range.maxx, limit.minx, limit.maxx)
+                    * overlap(range.miny, range.maxy, limit.miny, limit.maxy),
+            )
+        })
+        .sum()
+}
+
+#[test]
+fn test_tile_list() {
+    let list = "0/0/0\n\n 3/7/6\n14/8580/5738\n";
+    let tiles = read_tile_list(list.as_bytes()).unwrap();
+    assert_eq!(tiles, vec![(0, 0, 0), (3, 7, 6), (14, 8580, 5738)]);
+
+    let list = "0/0/0\n3/7\n";
+    let err = read_tile_list(list.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "Parse error: Invalid tile `3/7` in line 2");
+}
+
+#[test]
+fn test_descendants() {
+    let grid = Grid::web_mercator();
+    let limits = grid.tile_limits(grid.extent.clone(), 0);
+    assert_eq!(children(&grid, &limits, (0, 0, 0)).count(), 4);
+    assert_eq!(
+        children(&grid, &limits, (1, 1, 0)).collect::<Vec<_>>(),
+        vec![(2, 2, 0), (2, 2, 1), (2, 3, 0), (2, 3, 1)]
+    );
+    assert_eq!(descendant_count(&grid, &limits, (0, 0, 0), 2), 20);
+    assert_eq!(descendant_count(&grid, &limits, (1, 1, 0), 3), 20);
+    // 2x1 tiles at level 0
+    let wgs84 = Grid::wgs84();
+    let wgs84_limits = wgs84.tile_limits_xyz(wgs84.extent.clone(), 0);
+    assert_eq!(descendant_count(&wgs84, &wgs84_limits, (0, 1, 0), 2), 20);
+
+    let limits = vec![
+        ExtentInt {
+            minx: 0,
+            miny: 0,
+            maxx: 1,
+            maxy: 1,
+        },
+        ExtentInt {
+            minx: 1,
+            miny: 0,
+            maxx: 2,
+            maxy: 2,
+        },
+    ];
+    assert_eq!(
+        children(&grid, &limits, (0, 0, 0)).collect::<Vec<_>>(),
+        vec![(1, 1, 0), (1, 1, 1)]
+    );
+    assert_eq!(descendant_count(&grid, &limits, (0, 0, 0), 5), 2);
+}
@@ -0,0 +1,102 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile data with metadata
+
+use crate::tiletype;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+//  From https://github.com/mapbox/node-mbtiles/blob/master/lib/mbtiles.js
+//
+// ```javascript
+// var headers = tiletype.headers(row.tile_data);
+// headers['Last-Modified'] = new Date(mbtiles._stat.mtime).toUTCString();
+// headers['ETag'] = mbtiles._stat.size + '-' + Number(mbtiles._stat.mtime);
+// return callback(null, row.tile_data, headers);
+// ```
+
+/// Tile returned by a Tilesource
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tile {
+    pub data: Vec<u8>,
+    /// Modification time
+    pub mtime: Option<SystemTime>,
+    /// Entity tag (unquoted)
+    pub etag: Option<String>,
+    /// MIME type
+    pub content_type: String,
+    /// Compression, e.g. `gzip`
+    pub content_encoding: Option<String>,
+}
+
+impl Tile {
+    /// Tile with content type and encoding detected from `data`
+    pub fn new(data: Vec<u8>) -> Tile {
+        let tiletype = tiletype::detect(&data);
+        Tile {
+            data,
+            mtime: None,
+            etag: None,
+            content_type: tiletype.content_type().to_string(),
+            content_encoding: tiletype.content_encoding().map(|e| e.to_string()),
+        }
+    }
+
+    pub fn with_mtime(mut self, mtime: SystemTime) -> Tile {
+        self.mtime = Some(mtime);
+        self
+    }
+
+    pub fn with_etag(mut self, etag: String) -> Tile {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// HTTP headers
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Content-Type", self.content_type.clone())];
+        if let Some(encoding) = &self.content_encoding {
+            headers.push(("Content-Encoding", encoding.clone()));
+        }
+        if let Some(mtime) = self.mtime {
+            headers.push(("Last-Modified", httpdate::fmt_http_date(mtime)));
+        }
+        if let Some(etag) = &self.etag {
+            headers.push(("ETag", format!("\"{}\"", etag)));
+        }
+        headers
+    }
+}
+
+/// ETag from file size and modification time, as used by Tilelive backends
+pub fn stat_etag(size: u64, mtime: SystemTime) -> String {
+    let millis = mtime
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("{}-{}", size, millis)
+}
+
+#[test]
+fn test_headers() {
+    use std::time::Duration;
+    let mtime = UNIX_EPOCH + Duration::from_secs(784111777);
+    let tile = Tile::new(vec![0x1F, 0x8B, 0x08, 0x00])
+        .with_mtime(mtime)
+        .with_etag(stat_etag(4, mtime));
+    assert_eq!(
+        tile.headers(),
+        vec![
+            ("Content-Type", "application/x-protobuf".to_string()),
+            ("Content-Encoding", "gzip".to_string()),
+            ("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT".to_string()),
+            ("ETag", "\"4-784111777000\"".to_string()),
+        ]
+    );
+    assert_eq!(
+        Tile::new(b"\x89PNG\r\n\x1a\n".to_vec()).headers(),
+        vec![("Content-Type", "image/png".to_string())]
+    );
+}
@@ -5,7 +5,7 @@
 
 //! Tile connector API
 
-use url;
+use crate::error::Result;
 
 //  Methods from https://github.com/mapbox/tilelive/blob/master/lib/tilelive.js
 
@@ -29,7 +29,7 @@ use url;
 
 pub trait Tileconnector {
     /// Loads the Tileconnector object associated with the specified `uri`
-    fn load(uri: &str) -> Result<Self, url::ParseError>
+    fn load(uri: &str) -> Result<Self>
     where
         Self: std::marker::Sized;
 }
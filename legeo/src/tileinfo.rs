@@ -0,0 +1,80 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tileset metadata
+
+use crate::error::{Error, Result};
+use legeo_xyz::grid::Extent;
+use serde::{Deserialize, Serialize};
+
+//  From https://github.com/mapbox/tilelive/blob/master/API.md
+//
+// Tilesource.prototype.getInfo: data is a hash containing all the information.
+// Tilesink.prototype.putInfo: Info is a key-value hash with metadata.
+//
+// Keys as defined by the [TileJSON specification](https://github.com/mapbox/tilejson-spec)
+
+/// TileJSON metadata of a tileset. Unknown entries are `None`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+pub struct TileInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Tile format, e.g. `png` or `pbf`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// WGS84 bounding box `[w, s, e, n]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<[f64; 4]>,
+    /// Default position `[lon, lat, zoom]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center: Option<[f64; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minzoom: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxzoom: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<String>,
+    /// Vector tile layer descriptions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_layers: Option<serde_json::Value>,
+}
+
+impl TileInfo {
+    /// Parse TileJSON
+    pub fn from_tilejson(json: &str) -> Result<TileInfo> {
+        serde_json::from_str(json).map_err(Error::parse)
+    }
+
+    /// Serialize as TileJSON
+    pub fn to_tilejson(&self) -> String {
+        let mut json = serde_json::to_value(self).unwrap_or_default();
+        json["tilejson"] = "2.2.0".into();
+        json.to_string()
+    }
+
+    /// Bounds as extent
+    pub fn extent(&self) -> Option<Extent> {
+        self.bounds.map(|[minx, miny, maxx, maxy]| Extent {
+            minx,
+            miny,
+            maxx,
+            maxy,
+        })
+    }
+}
+
+#[test]
+fn test_tilejson() {
+    let json = r#"{"tilejson":"2.2.0","name":"osm","bounds":[-180,-85.0511,180,85.0511],"minzoom":0,"maxzoom":14,"vector_layers":[{"id":"water","fields":{}}]}"#;
+    let info = TileInfo::from_tilejson(json).unwrap();
+    assert_eq!(info.name, Some("osm".to_string()));
+    assert_eq!(info.maxzoom, Some(14));
+    assert_eq!(info.format, None);
+    assert_eq!(info.extent().unwrap().maxy, 85.0511);
+    assert_eq!(info.vector_layers.as_ref().unwrap()[0]["id"], "water");
+    assert_eq!(TileInfo::from_tilejson(&info.to_tilejson()).unwrap(), info);
+
+    assert!(TileInfo::from_tilejson("[]").is_err());
+}
@@ -5,7 +5,9 @@
 
 //! Tilesink trait API
 
+use crate::error::Result;
 use crate::tileconnector::Tileconnector;
+use crate::tileinfo::TileInfo;
 
 //  https://github.com/mapbox/tilelive/blob/master/API.md
 //
@@ -90,7 +92,22 @@ use crate::tileconnector::Tileconnector;
 
 /// Map tile destination
 pub trait Tilesink: Tileconnector {
+    /// Sinks which can't be written by multiple instances concurrently, e.g. single files.
+    fn single_writer() -> bool
+    where
+        Self: Sized,
+    {
+        false
+    }
     /// Stores a tile into the data store. Parameters are in XYZ format.
     /// `tile` must contain the compressed image.
-    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> std::io::Result<()>;
+    fn put_tile(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<()>;
+    /// Stores tileset metadata.
+    fn put_info(&self, _info: &TileInfo) -> Result<()> {
+        Ok(())
+    }
+    /// Commits cached tiles to the data store.
+    fn stop_writing(&self) -> Result<()> {
+        Ok(())
+    }
 }
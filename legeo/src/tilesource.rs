@@ -5,7 +5,10 @@
 
 //! Tilesource trait API
 
+use crate::error::Result;
+use crate::tile::Tile;
 use crate::tileconnector::Tileconnector;
+use crate::tileinfo::TileInfo;
 
 //  https://github.com/mapbox/tilelive/blob/master/API.md
 //
@@ -59,5 +62,11 @@ use crate::tileconnector::Tileconnector;
 
 /// Map tile source
 pub trait Tilesource: Tileconnector {
-    fn get_tile(&self, z: u8, x: u32, y: u32) -> std::io::Result<Vec<u8>>;
+    /// Obtains a tile. Parameters are in XYZ format.
+    /// Returns `Ok(None)` if the tile does not exist.
+    fn get_tile(&self, z: u8, x: u32, y: u32) -> Result<Option<Tile>>;
+    /// Obtains tileset metadata.
+    fn get_info(&self) -> Result<TileInfo> {
+        Ok(TileInfo::default())
+    }
 }
@@ -0,0 +1,250 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile format detection
+
+use std::io::Read;
+
+//  Port of https://github.com/mapbox/tiletype
+//
+// ```javascript
+// // Given a buffer of unknown format, return the format name.
+// module.exports.type = function(buffer) { ... }
+// // Return HTTP headers for a given tile buffer.
+// module.exports.headers = function(buffer) { ... }
+// ```
+
+/// Tile data format
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Gif,
+    /// Mapbox Vector Tile
+    Pbf,
+    Unknown,
+}
+
+/// Compression of tile data
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+    Brotli,
+    Zstd,
+}
+
+/// Detected format and compression of tile data
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TileType {
+    pub format: TileFormat,
+    pub compression: Compression,
+}
+
+impl TileFormat {
+    /// Known formats
+    pub const ALL: [TileFormat; 5] = [
+        TileFormat::Png,
+        TileFormat::Jpeg,
+        TileFormat::Webp,
+        TileFormat::Gif,
+        TileFormat::Pbf,
+    ];
+
+    /// Format name as used in TileJSON `format`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TileFormat::Png => "png",
+            TileFormat::Jpeg => "jpg",
+            TileFormat::Webp => "webp",
+            TileFormat::Gif => "gif",
+            TileFormat::Pbf => "pbf",
+            TileFormat::Unknown => "bin",
+        }
+    }
+
+    /// File extension
+    pub fn extension(&self) -> &'static str {
+        self.name()
+    }
+
+    /// MIME type
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TileFormat::Png => "image/png",
+            TileFormat::Jpeg => "image/jpeg",
+            TileFormat::Webp => "image/webp",
+            TileFormat::Gif => "image/gif",
+            TileFormat::Pbf => "application/x-protobuf",
+            TileFormat::Unknown => "application/octet-stream",
+        }
+    }
+
+    /// Format from name or file extension
+    pub fn from_name(name: &str) -> TileFormat {
+        match name {
+            "png" => TileFormat::Png,
+            "jpg" | "jpeg" => TileFormat::Jpeg,
+            "webp" => TileFormat::Webp,
+            "gif" => TileFormat::Gif,
+            "pbf" | "mvt" => TileFormat::Pbf,
+            _ => TileFormat::Unknown,
+        }
+    }
+}
+
+impl Compression {
+    /// HTTP `Content-Encoding` value
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zlib => Some("deflate"),
+            Compression::Brotli => Some("br"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+impl TileType {
+    pub fn content_type(&self) -> &'static str {
+        self.format.content_type()
+    }
+
+    pub fn content_encoding(&self) -> Option<&'static str> {
+        self.compression.content_encoding()
+    }
+
+    /// HTTP headers for tile data
+    pub fn headers(&self) -> Vec<(&'static str, &'static str)> {
+        let mut headers = vec![("Content-Type", self.content_type())];
+        if let Some(encoding) = self.content_encoding() {
+            headers.push(("Content-Encoding", encoding));
+        }
+        headers
+    }
+}
+
+/// Detect format of uncompressed data by its signature
+fn detect_format(data: &[u8]) -> TileFormat {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        TileFormat::Png
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        TileFormat::Jpeg
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        TileFormat::Gif
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        TileFormat::Webp
+    } else if data.first() == Some(&0x1A) {
+        // Tile.layers: field 3, length-delimited
+        TileFormat::Pbf
+    } else {
+        TileFormat::Unknown
+    }
+}
+
+/// Detect format and compression of tile data.
+/// Like Tilelive, gzip, zlib or zstd compressed data is assumed to be a vector tile.
+/// Brotli streams have no signature, so unknown data is decompressed
+/// and accepted as Brotli if its content has a known format.
+pub fn detect(data: &[u8]) -> TileType {
+    let compression = if data.starts_with(&[0x1F, 0x8B]) {
+        Compression::Gzip
+    } else if data.len() >= 2
+        && data[0] & 0x0F == 8
+        && (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 == 0
+    {
+        Compression::Zlib
+    } else if data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        Compression::Zstd
+    } else {
+        Compression::None
+    };
+    if compression != Compression::None {
+        return TileType {
+            format: TileFormat::Pbf,
+            compression,
+        };
+    }
+    let format = detect_format(data);
+    if format != TileFormat::Unknown {
+        return TileType {
+            format,
+            compression: Compression::None,
+        };
+    }
+    let format = detect_format(&brotli_prefix(data));
+    TileType {
+        format,
+        compression: if format == TileFormat::Unknown {
+            Compression::None
+        } else {
+            Compression::Brotli
+        },
+    }
+}
+
+/// First bytes of Brotli decompressed data, empty if invalid
+fn brotli_prefix(data: &[u8]) -> Vec<u8> {
+    let mut prefix = Vec::new();
+    let _ = brotli::Decompressor::new(data, 4096)
+        .take(16)
+        .read_to_end(&mut prefix);
+    prefix
+}
+
+#[test]
+fn test_detect() {
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    let tiletype = detect(&png);
+    assert_eq!(tiletype.format, TileFormat::Png);
+    assert_eq!(tiletype.headers(), vec![("Content-Type", "image/png")]);
+    assert_eq!(
+        detect(&[0xFF, 0xD8, 0xFF, 0xE0, 0, 0x10]).format,
+        TileFormat::Jpeg
+    );
+    assert_eq!(detect(b"GIF89a\x01\0\x01\0").format, TileFormat::Gif);
+    assert_eq!(detect(b"RIFF\x24\0\0\0WEBPVP8 ").format, TileFormat::Webp);
+    assert_eq!(
+        detect(b"\x1a\x04\x0a\x02id").content_type(),
+        "application/x-protobuf"
+    );
+
+    let gzip = detect(&[0x1F, 0x8B, 0x08, 0x00]);
+    assert_eq!(
+        gzip.headers(),
+        vec![
+            ("Content-Type", "application/x-protobuf"),
+            ("Content-Encoding", "gzip")
+        ]
+    );
+    assert_eq!(
+        detect(&[0x78, 0x9C, 0x01]).content_encoding(),
+        Some("deflate")
+    );
+    assert_eq!(detect(&[0x78, 0x01, 0x01]).compression, Compression::Zlib);
+    assert_eq!(
+        detect(&[0x28, 0xB5, 0x2F, 0xFD, 0x00]).content_encoding(),
+        Some("zstd")
+    );
+
+    // Uncompressed meta-block with vector tile bytes
+    let brotli = [0x0B, 0x02, 0x80, 0x1A, 0x04, 0x0A, 0x03];
+    assert_eq!(
+        detect(&brotli),
+        TileType {
+            format: TileFormat::Pbf,
+            compression: Compression::Brotli
+        }
+    );
+
+    assert_eq!(detect(b"").format, TileFormat::Unknown);
+    assert_eq!(
+        detect(b"<html>").headers(),
+        vec![("Content-Type", "application/octet-stream")]
+    );
+}
@@ -0,0 +1,208 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile transformations applied between reading and writing
+
+use crate::error::Result;
+use crate::raster::{RasterFormat, Reencode};
+use crate::tileinfo::TileInfo;
+use crate::tiletype::{self, Compression, TileFormat, TileType};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// Transformation of tile data
+pub trait Transform: fmt::Debug + Send + Sync {
+    /// Transform data of tile `(z, x, y)`
+    fn transform(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<Vec<u8>>;
+    /// Adapt tileset metadata to transformed tiles
+    fn transform_info(&self, info: TileInfo) -> TileInfo {
+        info
+    }
+}
+
+/// Sequence of transformations
+#[derive(Clone, Default, Debug)]
+pub struct Pipeline {
+    transforms: Vec<Arc<dyn Transform>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Append transformation
+    pub fn push(&mut self, transform: Arc<dyn Transform>) {
+        self.transforms.push(transform);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Apply all transformations in order
+    pub fn transform(&self, z: u8, x: u32, y: u32, data: Vec<u8>) -> Result<Vec<u8>> {
+        self.transforms
+            .iter()
+            .try_fold(data, |data, transform| transform.transform(z, x, y, data))
+    }
+
+    /// Apply metadata adaptions of all transformations in order
+    pub fn transform_info(&self, info: TileInfo) -> TileInfo {
+        self.transforms
+            .iter()
+            .fold(info, |info, transform| transform.transform_info(info))
+    }
+}
+
+impl From<Vec<Arc<dyn Transform>>> for Pipeline {
+    fn from(transforms: Vec<Arc<dyn Transform>>) -> Self {
+        Pipeline { transforms }
+    }
+}
+
+/// Decompress data with detected compression
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match tiletype::detect(data).compression {
+        Compression::None => return Ok(data.to_vec()),
+        Compression::Gzip => GzDecoder::new(data).read_to_end(&mut buf)?,
+        Compression::Zlib => ZlibDecoder::new(data).read_to_end(&mut buf)?,
+        Compression::Brotli => brotli::Decompressor::new(data, 4096).read_to_end(&mut buf)?,
+        Compression::Zstd => return zstd::decode_all(data),
+    };
+    Ok(buf)
+}
+
+/// Compress uncompressed data
+pub fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Compression::Zlib => {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        Compression::Brotli => {
+            let mut buf = Vec::new();
+            {
+                let mut encoder = brotli::CompressorWriter::new(&mut buf, 4096, 9, 22);
+                encoder.write_all(data)?;
+            }
+            Ok(buf)
+        }
+        Compression::Zstd => zstd::encode_all(data, 0),
+    }
+}
+
+/// Decompress compressed tiles. Uncompressed tiles are passed unchanged.
+#[derive(Debug)]
+pub struct Decompress;
+
+impl Transform for Decompress {
+    fn transform(&self, _z: u8, _x: u32, _y: u32, data: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(decompress(&data)?)
+    }
+}
+
+/// Compress tiles, recompressing tiles with another compression.
+/// Empty tiles and images are kept unchanged.
+#[derive(Debug)]
+pub struct Compress(pub Compression);
+
+impl Transform for Compress {
+    fn transform(&self, _z: u8, _x: u32, _y: u32, data: Vec<u8>) -> Result<Vec<u8>> {
+        let TileType {
+            format,
+            compression,
+        } = tiletype::detect(&data);
+        let image = format != TileFormat::Pbf && format != TileFormat::Unknown;
+        if data.is_empty() || image || compression == self.0 {
+            return Ok(data);
+        }
+        let data = if compression == Compression::None {
+            data
+        } else {
+            decompress(&data)?
+        };
+        Ok(compress(&data, self.0)?)
+    }
+}
+
+/// Built-in transformation by name: `decompress`, `gzip`, `brotli` or `zstd`,
+/// or raster re-encoding with `png[:colors]`, `jpeg[:quality]` or `webp[:quality|lossless]`
+pub fn builtin(name: &str) -> std::result::Result<Arc<dyn Transform>, String> {
+    match name {
+        "decompress" => Ok(Arc::new(Decompress)),
+        "gzip" => Ok(Arc::new(Compress(Compression::Gzip))),
+        "brotli" => Ok(Arc::new(Compress(Compression::Brotli))),
+        "zstd" => Ok(Arc::new(Compress(Compression::Zstd))),
+        _ => match name.parse::<RasterFormat>() {
+            Ok(format) => Ok(Arc::new(Reencode(format))),
+            Err(_) => Err(format!("Unknown transform `{}`", name)),
+        },
+    }
+}
+
+#[test]
+fn test_compression() {
+    let pbf = b"\x1a\x04\x0a\x02id".to_vec();
+    for name in &["gzip", "brotli", "zstd"] {
+        let compress = builtin(name).unwrap();
+        let compressed = compress.transform(0, 0, 0, pbf.clone()).unwrap();
+        let tiletype = tiletype::detect(&compressed);
+        assert_eq!(tiletype.format, TileFormat::Pbf);
+        assert_eq!(
+            tiletype.content_encoding(),
+            Some(if *name == "brotli" { "br" } else { name })
+        );
+        // Already compressed
+        assert_eq!(
+            compress.transform(0, 0, 0, compressed.clone()).unwrap(),
+            compressed
+        );
+        assert_eq!(Decompress.transform(0, 0, 0, compressed).unwrap(), pbf);
+    }
+    assert_eq!(Decompress.transform(0, 0, 0, pbf.clone()).unwrap(), pbf);
+    assert!(Compress(Compression::Gzip)
+        .transform(0, 0, 0, Vec::new())
+        .unwrap()
+        .is_empty());
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+    assert_eq!(
+        Compress(Compression::Gzip)
+            .transform(0, 0, 0, png.clone())
+            .unwrap(),
+        png
+    );
+    assert!(builtin("lzma").is_err());
+    assert!(builtin("webp:90").is_ok());
+}
+
+#[test]
+fn test_pipeline() {
+    let pbf = b"\x1a\x04\x0a\x02id".to_vec();
+    let gzipped = compress(&pbf, Compression::Gzip).unwrap();
+    let pipeline = Pipeline::from(vec![
+        builtin("decompress").unwrap(),
+        builtin("zstd").unwrap(),
+    ]);
+    let data = pipeline.transform(0, 0, 0, gzipped).unwrap();
+    assert_eq!(tiletype::detect(&data).compression, Compression::Zstd);
+    assert_eq!(decompress(&data).unwrap(), pbf);
+    assert_eq!(
+        Pipeline::new().transform(0, 0, 0, pbf.clone()).unwrap(),
+        pbf
+    );
+}